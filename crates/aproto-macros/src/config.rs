@@ -0,0 +1,119 @@
+use aproto_types::{
+    file::ProtobufFileDescriptor,
+    map::ValueTy,
+    scalar::{BytesTy, Ty},
+    Field, ProtobufMessageDescriptor,
+};
+use syn::parse::{Parse, ParseStream};
+
+/// Code generation settings, given as `#![aproto(...)]` attributes at the start
+/// of the macro input. They only change the generated Rust code, so the
+/// protobuf definitions after them stay valid for other protobuf tools.
+#[derive(Default)]
+pub struct Config {
+    /// How `bytes` fields are stored, set with `bytes = "vec"` (the default) or
+    /// `bytes = "bytes"`.
+    pub bytes: Option<BytesTy>,
+}
+
+impl Config {
+    /// Applies the settings to the messages of a parsed file.
+    pub fn apply(&self, file: &mut ProtobufFileDescriptor) {
+        for message in &mut file.messages {
+            self.apply_message(message);
+        }
+    }
+
+    fn apply_message(&self, descriptor: &mut ProtobufMessageDescriptor) {
+        if let Some(bytes) = &self.bytes {
+            set_bytes(&mut descriptor.fields.0, bytes);
+        }
+        for nested in &mut descriptor.messages {
+            self.apply_message(nested);
+        }
+    }
+}
+
+fn set_bytes(fields: &mut [Field], bytes: &BytesTy) {
+    for field in fields {
+        let ty = match field {
+            Field::Scalar(field) => &mut field.ty,
+            Field::Map(field) => match &mut field.value_ty {
+                ValueTy::Scalar(ty) => ty,
+                ValueTy::Message(..) => continue,
+            },
+            Field::Oneof(oneof) => {
+                set_bytes(&mut oneof.fields, bytes);
+                continue;
+            }
+            Field::Message(..) => continue,
+        };
+        if let Ty::Bytes(ty) = ty {
+            *ty = bytes.clone();
+        }
+    }
+}
+
+impl Parse for Config {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut config = Self::default();
+        for attr in input.call(syn::Attribute::parse_inner)? {
+            if !attr.path().is_ident("aproto") {
+                return Err(syn::Error::new_spanned(
+                    attr.path(),
+                    "expected `aproto` attribute",
+                ));
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("bytes") {
+                    let value = meta.value()?.parse::<syn::LitStr>()?;
+                    config.bytes = Some(match value.value().as_str() {
+                        "vec" => BytesTy::Vec,
+                        "bytes" => BytesTy::Bytes,
+                        _ => {
+                            return Err(syn::Error::new(
+                                value.span(),
+                                "expected `\"vec\"` or `\"bytes\"`",
+                            ))
+                        }
+                    });
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown aproto setting"))
+                }
+            })?;
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::quote;
+
+    #[test]
+    fn test_parse_config() {
+        let config = syn::parse2::<Config>(quote!()).unwrap();
+        assert_eq!(config.bytes, None);
+
+        let config = syn::parse2::<Config>(quote!(#![aproto(bytes = "bytes")])).unwrap();
+        assert_eq!(config.bytes, Some(BytesTy::Bytes));
+
+        let cases = [
+            (
+                quote!(#![aproto(bytes = "slice")]),
+                "expected `\"vec\"` or `\"bytes\"`",
+            ),
+            (quote!(#![aproto(packed = true)]), "unknown aproto setting"),
+            (
+                quote!(#![serde(bytes = "bytes")]),
+                "expected `aproto` attribute",
+            ),
+        ];
+        for (input, expected) in cases {
+            let err = syn::parse2::<Config>(input).err().unwrap();
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use aproto_types::enums::ProtobufEnumDescriptor;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::utils::{rust_ident, upper_camel_case};
+
+/// Generates a Rust enum for a protobuf enum, convertible to and from the
+/// `i32` it is encoded as.
+pub fn generate(descriptor: &ProtobufEnumDescriptor) -> syn::Result<TokenStream> {
+    let name = syn::Ident::new(&descriptor.name, Span::call_site());
+    let enum_name = &descriptor.name;
+    let mut variants = Vec::<(syn::Ident, i32)>::new();
+    for value in &descriptor.values {
+        let variant = variant_ident(&value.name);
+        if let Some(i) = variants.iter().position(|(other, _)| *other == variant) {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                format!(
+                    "enum values `{}` and `{}` both generate `{enum_name}::{variant}`",
+                    descriptor.values[i].name, value.name,
+                ),
+            ));
+        }
+        variants.push((variant, value.number));
+    }
+
+    let definitions = variants.iter().enumerate().map(|(i, (variant, number))| {
+        // The first value is the proto3 default
+        let default = (i == 0).then(|| quote!(#[default]));
+        quote!(#default #variant = #number)
+    });
+    let conversions = variants
+        .iter()
+        .map(|(variant, number)| quote!(#number => ::std::result::Result::Ok(Self::#variant),));
+
+    Ok(quote! {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+        #[repr(i32)]
+        pub enum #name {
+            #(#definitions,)*
+        }
+
+        impl ::std::convert::TryFrom<i32> for #name {
+            type Error = ::aproto::AprotoError;
+
+            fn try_from(value: i32) -> ::std::result::Result<Self, Self::Error> {
+                match value {
+                    #(#conversions)*
+                    _ => ::std::result::Result::Err(::aproto::AprotoError::UnknownEnumValue {
+                        enumeration: #enum_name,
+                        value,
+                    }),
+                }
+            }
+        }
+
+        impl ::std::convert::From<#name> for i32 {
+            fn from(value: #name) -> i32 {
+                value as i32
+            }
+        }
+    })
+}
+
+/// Converts an `UPPER_SNAKE_CASE` protobuf value name to an `UpperCamelCase`
+/// Rust variant name. A value named `SELF` becomes `Self_`.
+fn variant_ident(name: &str) -> syn::Ident {
+    rust_ident(&upper_camel_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variant_ident() {
+        assert_eq!(variant_ident("STATUS_ACTIVE"), "StatusActive");
+        assert_eq!(variant_ident("SELF"), "Self_");
+    }
+
+    #[test]
+    fn test_duplicate_variants() {
+        let descriptor = syn::parse2::<ProtobufEnumDescriptor>(quote!(
+            enum Kind {
+                FOO_BAR = 0;
+                foo_bar = 1;
+            }
+        ))
+        .unwrap();
+        let err = generate(&descriptor).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "enum values `FOO_BAR` and `foo_bar` both generate `Kind::FooBar`"
+        );
+    }
+}
@@ -0,0 +1,23 @@
+use aproto_types::file::ProtobufFileDescriptor;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::{enums, message};
+
+/// Generates every top-level message and enum of a protobuf file.
+pub fn generate(file: &ProtobufFileDescriptor) -> syn::Result<TokenStream> {
+    let messages = file
+        .messages
+        .iter()
+        .map(|descriptor| message::generate(file, descriptor))
+        .collect::<syn::Result<Vec<_>>>()?;
+    let enums = file
+        .enums
+        .iter()
+        .map(enums::generate)
+        .collect::<syn::Result<Vec<_>>>()?;
+    Ok(quote! {
+        #(#messages)*
+        #(#enums)*
+    })
+}
@@ -0,0 +1,170 @@
+use std::path::Path;
+
+use aproto_types::{
+    file::ProtobufFileDescriptor,
+    resolve,
+    source::{self, Location},
+    utils::combine,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{ParseStream, Parser};
+
+use crate::{config::Config, file};
+
+/// Generates the messages and enums of a `.proto` file, given by its path
+/// relative to the crate's manifest directory.
+///
+/// The files it imports, and the files those import, are read from paths
+/// relative to the directory of the included file. Their types can be referred
+/// to but are not generated, as they are expected to be included next to it.
+pub fn generate(config: &Config, path: &syn::LitStr) -> syn::Result<TokenStream> {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| syn::Error::new(path.span(), "CARGO_MANIFEST_DIR is not set"))?;
+    let root = Path::new(&manifest_dir).join(path.value());
+    let include_dir = root.parent().unwrap_or(&root).to_path_buf();
+
+    let (mut file, locations) = load(path, &path.value(), &root)?;
+    let mut loaded = vec![root.clone()];
+    let mut imports = file.imports.clone();
+    while let Some(import) = imports.pop() {
+        let import_path = include_dir.join(import.value());
+        if loaded.contains(&import_path) {
+            continue;
+        }
+        let (dependency, _) = load(&import, &import.value(), &import_path)?;
+        imports.extend(dependency.imports.iter().cloned());
+        file.dependencies.push(dependency);
+        loaded.push(import_path);
+    }
+
+    config.apply(&mut file);
+    combine(
+        file.messages
+            .iter()
+            .zip(locations)
+            .filter_map(|(message, location)| {
+                let error = resolve::check_message(&file, message).err()?;
+                Some(in_file(path, &path.value(), location, error))
+            }),
+    )?;
+    let generated = file::generate(&file)?;
+    // Reading the files through `include_bytes!` rebuilds the crate whenever
+    // one of them changes
+    let tracked = loaded
+        .iter()
+        .map(|loaded| {
+            loaded.to_str().ok_or_else(|| {
+                syn::Error::new(
+                    path.span(),
+                    format!("`{}` is not valid UTF-8", loaded.display()),
+                )
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    Ok(quote! {
+        #generated
+        #(const _: &[u8] = ::std::include_bytes!(#tracked);)*
+    })
+}
+
+/// Reads and parses a single file, together with the location of each of its
+/// top-level messages. `name` is the path as written, used to report errors in
+/// the file at the span of `lit`.
+fn load(
+    lit: &syn::LitStr,
+    name: &str,
+    path: &Path,
+) -> syn::Result<(ProtobufFileDescriptor, Vec<Location>)> {
+    let text = std::fs::read_to_string(path).map_err(|error| {
+        syn::Error::new(lit.span(), format!("failed to read `{name}`: {error}"))
+    })?;
+    let statements = source::tokenize(&text).map_err(|error| {
+        let message = syn::Error::new(lit.span(), error.message);
+        in_file(lit, name, error.location, message)
+    })?;
+
+    let mut file = ProtobufFileDescriptor::default();
+    let mut locations = Vec::new();
+    let mut errors = Vec::new();
+    for (i, statement) in statements.into_iter().enumerate() {
+        // Only the first statement may be `syntax`, which decides how the
+        // others are parsed
+        let parsed = if i == 0 {
+            syn::parse2(statement.tokens).map(|first| file = first)
+        } else {
+            let parse = |input: ParseStream| file.parse_statement(input);
+            parse.parse2(statement.tokens)
+        };
+        match parsed {
+            Ok(()) => locations.resize(file.messages.len(), statement.location),
+            Err(error) => errors.push(in_file(lit, name, statement.location, error)),
+        }
+    }
+    combine(errors)?;
+    Ok((file, locations))
+}
+
+/// Moves errors found in a file to the span of the literal naming it, and
+/// prefixes them with the file's name and the location they were found at, as
+/// there is no span inside the file to point at.
+fn in_file(lit: &syn::LitStr, name: &str, location: Location, error: syn::Error) -> syn::Error {
+    error
+        .into_iter()
+        .map(|error| syn::Error::new(lit.span(), format!("{name}:{location}: {error}")))
+        .reduce(|mut error, next| {
+            error.combine(next);
+            error
+        })
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proc_macro2::Span;
+
+    #[test]
+    fn test_errors_are_located() {
+        let dir = std::env::temp_dir().join(format!("aproto-include-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cases = [
+            (
+                "syntax = \"proto3\";\n\nmessage A {\n  int32 a = 1;\n}\n\
+                 message B { int32 b = 0; }\nenum C {}\n",
+                vec![
+                    "x.proto:6:1: field number must be at least 1",
+                    "x.proto:7:1: enum must have at least one value",
+                ],
+            ),
+            (
+                "message A {\n  Missing a = 1;\n}\n\nmessage B {\n  message C {\n    \
+                 Nope c = 1;\n  }\n}\n",
+                vec![
+                    "x.proto:1:1: unknown type `Missing`",
+                    "x.proto:5:1: unknown type `Nope`",
+                ],
+            ),
+            (
+                "syntax = \"proto3\";\nmessage A {\n  string a = 1 [default = \"\\q\"];\n}\n",
+                vec!["x.proto:3:27: unknown escape `\\q`"],
+            ),
+        ];
+        for (text, expected) in cases {
+            let path = dir.join("x.proto");
+            std::fs::write(&path, text).unwrap();
+            let lit = syn::LitStr::new(path.to_str().unwrap(), Span::call_site());
+            let errors = generate(&Config::default(), &lit)
+                .err()
+                .unwrap()
+                .into_iter()
+                .map(|error| {
+                    let error = error.to_string();
+                    error.replace(path.to_str().unwrap(), "x.proto")
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(errors, expected);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
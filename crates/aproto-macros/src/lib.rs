@@ -0,0 +1,141 @@
+use aproto_types::{file::ProtobufFileDescriptor, resolve, ProtobufDescriptor};
+use config::Config;
+use proc_macro::TokenStream;
+use syn::parse::ParseStream;
+
+mod config;
+mod enums;
+mod file;
+mod include;
+mod message;
+mod oneof;
+mod scope;
+mod utils;
+
+/// Generates Rust code from a protobuf message or enum definition.
+///
+/// A message becomes a struct implementing `aproto::Message`, and an enum a
+/// Rust enum convertible to and from `i32`. Types the definition does not
+/// declare itself are taken to be messages generated next to the invocation;
+/// use `proto!` when a message refers to an enum declared elsewhere.
+///
+/// ```ignore
+/// aproto::message! {
+///     message Person {
+///         uint32 id = 1;
+///         bool active = 2;
+///     }
+/// }
+///
+/// aproto::message! {
+///     enum Status {
+///         UNKNOWN = 0;
+///         ACTIVE = 1;
+///     }
+/// }
+/// ```
+///
+/// `bytes` fields are stored as `Vec<u8>`. An `#![aproto(bytes = "bytes")]`
+/// attribute before the definition stores them as `bytes::Bytes` instead. It
+/// is a setting of the macro rather than a protobuf option, so the definition
+/// itself stays plain protobuf.
+///
+/// ```ignore
+/// aproto::message! {
+///     #![aproto(bytes = "bytes")]
+///     message Blob {
+///         bytes data = 1;
+///     }
+/// }
+/// ```
+#[proc_macro]
+pub fn message(input: TokenStream) -> TokenStream {
+    let (config, descriptor) = syn::parse_macro_input!(input with parse_message);
+    let mut file = ProtobufFileDescriptor::from(descriptor);
+    config.apply(&mut file);
+    file::generate(&file)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generates Rust code from a whole protobuf file, with an optional `syntax`
+/// and `package` statement followed by any number of messages and enums.
+///
+/// Messages and enums may refer to each other regardless of the order they
+/// are declared in, and every type a field refers to must be declared in the
+/// file. The file may start with the same `#![aproto(...)]` settings as
+/// `message!`.
+///
+/// ```ignore
+/// aproto::proto! {
+///     syntax = "proto3";
+///     package people;
+///
+///     message Person {
+///         uint32 id = 1;
+///         Status status = 2;
+///     }
+///
+///     enum Status {
+///         UNKNOWN = 0;
+///         ACTIVE = 1;
+///     }
+/// }
+/// ```
+#[proc_macro]
+pub fn proto(input: TokenStream) -> TokenStream {
+    let (config, mut file) = syn::parse_macro_input!(input with parse_file);
+    config.apply(&mut file);
+    if let Some(import) = file.imports.first() {
+        return syn::Error::new(
+            import.span(),
+            "imports are only supported by `include_proto!`",
+        )
+        .into_compile_error()
+        .into();
+    }
+    resolve::check(&file)
+        .and_then(|()| file::generate(&file))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generates Rust code from a `.proto` file on disk, given by its path
+/// relative to the crate's `Cargo.toml`. The file is read like the input of
+/// `proto!`, with comments, single quoted strings and C escapes as protobuf
+/// allows them, and the crate is rebuilt when it changes.
+///
+/// Imported files are read from paths relative to the directory of the
+/// included file. Their types are not generated, so include them next to it.
+/// Services and extensions are skipped, and errors in a file are reported
+/// with the line and column of the statement they were found in.
+///
+/// The path may be preceded by the same `#![aproto(...)]` settings as
+/// `message!`, which keeps them out of the `.proto` file.
+///
+/// ```ignore
+/// aproto::include_proto!("schemas/common.proto");
+/// // `user.proto` imports "common.proto"
+/// aproto::include_proto!("schemas/user.proto");
+///
+/// aproto::include_proto!(#![aproto(bytes = "bytes")] "schemas/blob.proto");
+/// ```
+#[proc_macro]
+pub fn include_proto(input: TokenStream) -> TokenStream {
+    let (config, path) = syn::parse_macro_input!(input with parse_include);
+    include::generate(&config, &path)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn parse_message(input: ParseStream) -> syn::Result<(Config, ProtobufDescriptor)> {
+    Ok((input.parse()?, input.parse()?))
+}
+
+fn parse_file(input: ParseStream) -> syn::Result<(Config, ProtobufFileDescriptor)> {
+    Ok((input.parse()?, input.parse()?))
+}
+
+fn parse_include(input: ParseStream) -> syn::Result<(Config, syn::LitStr)> {
+    Ok((input.parse()?, input.parse()?))
+}
@@ -0,0 +1,688 @@
+use aproto_types::{
+    file::{ProtobufFileDescriptor, Syntax},
+    map::{MapField, ValueTy},
+    message::MessageField,
+    oneof::OneofField,
+    options::{DefaultValue, FieldOptions},
+    scalar::{self, ScalarField},
+    Field, Label, ProtobufMessageDescriptor,
+};
+use proc_macro2::{Literal, Span, TokenStream};
+use quote::quote;
+
+use crate::{enums, oneof, scope::Scope, utils::rust_ident};
+
+/// Generates the Rust struct for a single protobuf message, together with its
+/// `aproto::Message` implementation. Messages and enums declared inside the
+/// message are generated in a module named after it.
+pub fn generate(
+    file: &ProtobufFileDescriptor,
+    descriptor: &ProtobufMessageDescriptor,
+) -> syn::Result<TokenStream> {
+    generate_in(&Scope::new(file, descriptor))
+}
+
+fn generate_in(scope: &Scope) -> syn::Result<TokenStream> {
+    let descriptor = scope.message();
+    let name = syn::Ident::new(&descriptor.name, Span::call_site());
+    let fields = descriptor
+        .fields
+        .0
+        .iter()
+        .map(|field| lower_enum_field(scope, field))
+        .collect::<Vec<_>>();
+
+    let struct_fields = fields.iter().map(|field| struct_field(scope, field));
+    let encode = fields.iter().map(|field| encode(scope, field));
+    let encoded_len = fields.iter().map(|field| encoded_len(scope, field));
+    let merge = fields.iter().map(|field| merge(scope, field));
+    let check_initialized = (scope.syntax() == Syntax::Proto2).then(|| {
+        let checks = fields.iter().map(|field| check_initialized(scope, field));
+        quote! {
+            fn check_initialized(&self) -> ::std::result::Result<(), ::aproto::AprotoError> {
+                #(#checks)*
+                ::std::result::Result::Ok(())
+            }
+        }
+    });
+    let getters = fields.iter().filter_map(getter);
+    let json_names = json_names(&fields)
+        .into_iter()
+        .map(|(name, json_name)| quote!((#name, #json_name)));
+    let nested = nested_module(scope, &fields)?;
+
+    Ok(quote! {
+        #[derive(Clone, Debug, PartialEq, Default)]
+        pub struct #name {
+            #(#struct_fields,)*
+        }
+
+        #[allow(deprecated)]
+        impl ::aproto::Message for #name {
+            #[allow(unused_variables)]
+            fn encode_raw(&self, buf: &mut impl ::aproto::bytes::BufMut) {
+                #(#encode)*
+            }
+
+            fn merge_field(
+                &mut self,
+                tag: u32,
+                wire_type: ::aproto::WireType,
+                buf: &mut impl ::aproto::bytes::Buf,
+            ) -> ::std::result::Result<(), ::aproto::AprotoError> {
+                match tag {
+                    #(#merge)*
+                    _ => ::aproto::encoding::skip_field(wire_type, tag, buf),
+                }
+            }
+
+            fn encoded_len(&self) -> usize {
+                0 #(+ #encoded_len)*
+            }
+
+            fn clear(&mut self) {
+                *self = Self::default();
+            }
+
+            #check_initialized
+        }
+
+        #[allow(deprecated)]
+        impl #name {
+            /// The name of each field in the JSON mapping, by its protobuf
+            /// name, as set with `json_name` or else in `lowerCamelCase`.
+            pub const JSON_NAMES: &'static [(&'static str, &'static str)] = &[#(#json_names),*];
+
+            #(#getters)*
+        }
+
+        #nested
+    })
+}
+
+/// The protobuf and JSON names of every field, including oneof members.
+fn json_names(fields: &[Field]) -> Vec<(String, String)> {
+    fields
+        .iter()
+        .flat_map(|field| match (field, field.options()) {
+            (Field::Oneof(oneof), _) => json_names(&oneof.fields),
+            (field, Some(options)) => {
+                vec![(field.name().to_string(), options.json_name(field.name()))]
+            }
+            (_, None) => Vec::new(),
+        })
+        .collect()
+}
+
+/// Enum fields are stored and encoded as `int32`, which keeps values unknown
+/// to this version of the enum intact. Their default becomes the number of
+/// the enum value it names.
+fn lower_enum_field(scope: &Scope, field: &Field) -> Field {
+    match field {
+        Field::Message(field) if scope.is_enum(&field.ty.name) => Field::Scalar(ScalarField {
+            name: field.name.clone(),
+            label: field.label.clone(),
+            ty: scalar::Ty::Int32,
+            tag: field.tag,
+            options: FieldOptions {
+                default: enum_default(scope, field),
+                ..field.options.clone()
+            },
+        }),
+        Field::Map(field) if matches!(&field.value_ty, ValueTy::Message(ty) if scope.is_enum(&ty.name)) => {
+            Field::Map(MapField {
+                value_ty: ValueTy::Scalar(scalar::Ty::Int32),
+                ..field.clone()
+            })
+        }
+        Field::Oneof(oneof) => Field::Oneof(OneofField {
+            name: oneof.name.clone(),
+            span: oneof.span,
+            fields: oneof
+                .fields
+                .iter()
+                .map(|field| lower_enum_field(scope, field))
+                .collect(),
+        }),
+        field => field.clone(),
+    }
+}
+
+/// The number of an enum field's default: the value named by its `default`
+/// option, or else the enum's first value.
+fn enum_default(scope: &Scope, field: &MessageField) -> Option<DefaultValue> {
+    let e = scope.resolve_enum(&field.ty.name)?;
+    let value = match &field.options.default {
+        Some(DefaultValue::Ident(name)) => e.value(name)?,
+        _ => e.default_value(),
+    };
+    Some(DefaultValue::Int(value.number.into()))
+}
+
+fn nested_module(scope: &Scope, fields: &[Field]) -> syn::Result<TokenStream> {
+    let descriptor = scope.message();
+    let messages = descriptor
+        .messages
+        .iter()
+        .map(|message| generate_in(&scope.nested(message)))
+        .collect::<syn::Result<Vec<_>>>()?;
+    let enums = descriptor
+        .enums
+        .iter()
+        .map(enums::generate)
+        .collect::<syn::Result<Vec<_>>>()?;
+    let mut oneofs = Vec::new();
+    let mut names = Vec::new();
+    for field in fields {
+        if let Field::Oneof(oneof) = field {
+            // The enum of a oneof lives next to the nested types
+            let name = oneof::enum_ident(oneof).to_string();
+            if descriptor.declares(&name) || names.contains(&name) {
+                return Err(syn::Error::new(
+                    oneof.span,
+                    format!(
+                        "oneof `{}` generates the enum `{name}`, which clashes with another type in `{}`",
+                        oneof.name,
+                        scope.full_name(),
+                    ),
+                ));
+            }
+            names.push(name);
+            oneofs.push(oneof::generate(scope, oneof));
+        }
+    }
+    if messages.is_empty() && descriptor.enums.is_empty() && oneofs.is_empty() {
+        return Ok(TokenStream::new());
+    }
+
+    let module = scope.nested_module();
+    Ok(quote! {
+        pub mod #module {
+            #(#messages)*
+            #(#enums)*
+            #(#oneofs)*
+        }
+    })
+}
+
+fn struct_field(scope: &Scope, field: &Field) -> TokenStream {
+    let (name, ty) = match field {
+        Field::Scalar(field) => (&field.name, scalar_type(field)),
+        Field::Message(field) => (&field.name, message_type(scope, field)),
+        Field::Map(field) => (&field.name, map_type(scope, field)),
+        Field::Oneof(field) => (&field.name, oneof::struct_type(scope, field)),
+    };
+    let name = field_ident(name);
+    let deprecated = field
+        .options()
+        .is_some_and(|options| options.deprecated)
+        .then(|| quote!(#[deprecated]));
+    quote!(#deprecated pub #name: #ty)
+}
+
+fn encode(scope: &Scope, field: &Field) -> TokenStream {
+    match field {
+        Field::Scalar(field) => encode_scalar(scope, field),
+        Field::Message(field) => encode_message(field),
+        Field::Map(field) => encode_map(field),
+        Field::Oneof(field) => oneof::encode(scope, field),
+    }
+}
+
+fn encoded_len(scope: &Scope, field: &Field) -> TokenStream {
+    match field {
+        Field::Scalar(field) => encoded_len_scalar(scope, field),
+        Field::Message(field) => encoded_len_message(field),
+        Field::Map(field) => encoded_len_map(field),
+        Field::Oneof(field) => oneof::encoded_len(scope, field),
+    }
+}
+
+fn merge(scope: &Scope, field: &Field) -> TokenStream {
+    let message = scope.full_name();
+    let message = message.as_str();
+    match field {
+        Field::Scalar(field) => merge_arm(message, &field.name, field.tag, decode_scalar(field)),
+        Field::Message(field) => merge_arm(message, &field.name, field.tag, decode_message(field)),
+        Field::Map(field) => merge_arm(message, &field.name, field.tag, decode_map(field)),
+        Field::Oneof(field) => oneof::merge(scope, field),
+    }
+}
+
+/// Decode errors are wrapped so they name the message and field that failed.
+pub fn merge_arm(message: &str, field_name: &str, tag: u32, decode: TokenStream) -> TokenStream {
+    let error = field_error(message, field_name, quote!(error));
+    quote! {
+        #tag => #decode.map_err(|error| #error),
+    }
+}
+
+/// An error naming the message and field it occurred in.
+pub fn field_error(message: &str, field_name: &str, source: TokenStream) -> TokenStream {
+    quote! {
+        ::aproto::AprotoError::Field {
+            message: #message,
+            field: #field_name,
+            source: ::std::boxed::Box::new(#source),
+        }
+    }
+}
+
+/// Checks that a required field is set, and that the messages the field holds
+/// have their own required fields set.
+fn check_initialized(scope: &Scope, field: &Field) -> TokenStream {
+    let message = scope.full_name();
+    let message = message.as_str();
+    match field {
+        Field::Scalar(field) => check_required(message, &field.name, field.label.as_ref()),
+        Field::Message(field) => {
+            let name = field_ident(&field.name);
+            let required = check_required(message, &field.name, field.label.as_ref());
+            let check = check_message(message, &field.name);
+            match field.label {
+                Some(Label::Repeated) => quote! {
+                    for value in &self.#name {
+                        #check
+                    }
+                },
+                _ => quote! {
+                    #required
+                    if let ::std::option::Option::Some(value) = &self.#name {
+                        #check
+                    }
+                },
+            }
+        }
+        Field::Map(field) if matches!(field.value_ty, ValueTy::Message(..)) => {
+            let name = field_ident(&field.name);
+            let check = check_message(message, &field.name);
+            quote! {
+                for value in self.#name.values() {
+                    #check
+                }
+            }
+        }
+        Field::Map(..) => TokenStream::new(),
+        Field::Oneof(field) => oneof::check_initialized(scope, field),
+    }
+}
+
+fn check_required(message: &str, field_name: &str, label: Option<&Label>) -> TokenStream {
+    if label != Some(&Label::Required) {
+        return TokenStream::new();
+    }
+    let name = field_ident(field_name);
+    let error = field_error(
+        message,
+        field_name,
+        quote!(::aproto::AprotoError::MissingRequiredField),
+    );
+    quote! {
+        if self.#name.is_none() {
+            return ::std::result::Result::Err(#error);
+        }
+    }
+}
+
+/// Checks the required fields of the message `value`, held by a field.
+pub fn check_message(message: &str, field_name: &str) -> TokenStream {
+    let error = field_error(message, field_name, quote!(error));
+    quote! {
+        ::aproto::Message::check_initialized(value).map_err(|error| #error)?;
+    }
+}
+
+/// Whether a singular field records if it is set, and so is stored as an
+/// `Option`. Fields labeled `optional` or `required` do, while unlabeled
+/// proto3 fields are taken to be unset when they hold their default.
+fn has_presence(label: Option<&Label>) -> bool {
+    matches!(label, Some(Label::Optional | Label::Required))
+}
+
+/// An expression that is true when a scalar holds something other than its
+/// type's default. Proto3 fields without presence are not encoded otherwise.
+fn is_set(ty: &scalar::Ty, value: TokenStream) -> TokenStream {
+    match ty {
+        // Negative zero is encoded, like any other value that is not all zero
+        scalar::Ty::Float | scalar::Ty::Double => quote!(#value.to_bits() != 0),
+        scalar::Ty::String | scalar::Ty::Bytes(..) => quote!(!#value.is_empty()),
+        scalar::Ty::Bool => quote!(#value),
+        _ => quote!(#value != 0),
+    }
+}
+
+/// Fields stored as an `Option` get a method returning their value, or their
+/// default when they are not set.
+fn getter(field: &Field) -> Option<TokenStream> {
+    let Field::Scalar(field) = field else {
+        return None;
+    };
+    if !has_presence(field.label.as_ref()) {
+        return None;
+    }
+    let name = field_ident(&field.name);
+    let default = field.options.default.as_ref();
+    let getter = match &field.ty {
+        scalar::Ty::String => {
+            let default = match default {
+                Some(DefaultValue::String(value)) => value.as_str(),
+                _ => "",
+            };
+            quote! {
+                pub fn #name(&self) -> &str {
+                    self.#name.as_deref().unwrap_or(#default)
+                }
+            }
+        }
+        scalar::Ty::Bytes(..) => {
+            let default = Literal::byte_string(match default {
+                Some(DefaultValue::Bytes(value)) => value.as_slice(),
+                _ => b"",
+            });
+            quote! {
+                pub fn #name(&self) -> &[u8] {
+                    self.#name.as_deref().unwrap_or(#default)
+                }
+            }
+        }
+        ty => {
+            let rust_type = ty.rust_type();
+            let value = match default {
+                Some(default) => {
+                    let default = default_value(ty, default);
+                    quote!(self.#name.unwrap_or(#default))
+                }
+                None => quote!(self.#name.unwrap_or_default()),
+            };
+            quote! {
+                pub fn #name(&self) -> #rust_type {
+                    #value
+                }
+            }
+        }
+    };
+    Some(getter)
+}
+
+/// A numeric or `bool` default as a Rust expression of the field's type.
+fn default_value(ty: &scalar::Ty, default: &DefaultValue) -> TokenStream {
+    match default {
+        DefaultValue::Bool(value) => quote!(#value),
+        DefaultValue::Int(value) if matches!(ty, scalar::Ty::Float | scalar::Ty::Double) => {
+            let value = Literal::f64_unsuffixed(*value as f64);
+            quote!(#value)
+        }
+        DefaultValue::Int(value) => {
+            let value = Literal::i128_unsuffixed(*value);
+            quote!(#value)
+        }
+        DefaultValue::Float(bits) if *ty == scalar::Ty::Float => {
+            quote!(::std::primitive::f64::from_bits(#bits) as f32)
+        }
+        DefaultValue::Float(bits) => quote!(::std::primitive::f64::from_bits(#bits)),
+        DefaultValue::String(..) | DefaultValue::Bytes(..) | DefaultValue::Ident(..) => {
+            unreachable!("checked against the field's type when parsed")
+        }
+    }
+}
+
+fn encode_scalar(scope: &Scope, field: &ScalarField) -> TokenStream {
+    let name = field_ident(&field.name);
+    let module = encoding_module(field);
+    let tag = field.tag;
+    match field.label {
+        Some(Label::Repeated) if field.is_packed(scope.syntax()) => {
+            quote!(#module::encode_packed(#tag, &self.#name, buf);)
+        }
+        Some(Label::Repeated) => quote!(#module::encode_repeated(#tag, &self.#name, buf);),
+        _ if has_presence(field.label.as_ref()) => quote! {
+            if let ::std::option::Option::Some(value) = &self.#name {
+                #module::encode(#tag, value, buf);
+            }
+        },
+        _ => {
+            let is_set = is_set(&field.ty, quote!(self.#name));
+            quote! {
+                if #is_set {
+                    #module::encode(#tag, &self.#name, buf);
+                }
+            }
+        }
+    }
+}
+
+fn encoded_len_scalar(scope: &Scope, field: &ScalarField) -> TokenStream {
+    let name = field_ident(&field.name);
+    let module = encoding_module(field);
+    let tag = field.tag;
+    match field.label {
+        Some(Label::Repeated) if field.is_packed(scope.syntax()) => {
+            quote!(#module::encode_len_packed(#tag, &self.#name))
+        }
+        Some(Label::Repeated) => quote!(#module::encode_len_repeated(#tag, &self.#name)),
+        _ if has_presence(field.label.as_ref()) => quote! {
+            self.#name
+                .as_ref()
+                .map_or(0, |value| #module::encode_len(#tag, value))
+        },
+        _ => {
+            let is_set = is_set(&field.ty, quote!(self.#name));
+            quote! {
+                if #is_set {
+                    #module::encode_len(#tag, &self.#name)
+                } else {
+                    0
+                }
+            }
+        }
+    }
+}
+
+fn decode_scalar(field: &ScalarField) -> TokenStream {
+    let name = field_ident(&field.name);
+    let module = encoding_module(field);
+    match field.label {
+        Some(Label::Repeated) => quote!(#module::decode_repeated(wire_type, &mut self.#name, buf)),
+        _ if has_presence(field.label.as_ref()) => quote! {
+            #module::decode(
+                wire_type,
+                self.#name.get_or_insert_with(::std::default::Default::default),
+                buf,
+            )
+        },
+        _ => quote!(#module::decode(wire_type, &mut self.#name, buf)),
+    }
+}
+
+fn encode_message(field: &MessageField) -> TokenStream {
+    let name = field_ident(&field.name);
+    let module = message_module(field);
+    let tag = field.tag;
+    match field.label {
+        Some(Label::Repeated) => quote!(#module::encode_repeated(#tag, &self.#name, buf);),
+        _ => quote! {
+            if let ::std::option::Option::Some(value) = &self.#name {
+                #module::encode(#tag, value, buf);
+            }
+        },
+    }
+}
+
+fn encoded_len_message(field: &MessageField) -> TokenStream {
+    let name = field_ident(&field.name);
+    let module = message_module(field);
+    let tag = field.tag;
+    match field.label {
+        Some(Label::Repeated) => quote!(#module::encode_len_repeated(#tag, &self.#name)),
+        _ => quote! {
+            self.#name
+                .as_ref()
+                .map_or(0, |value| #module::encode_len(#tag, value))
+        },
+    }
+}
+
+/// A singular message field that appears more than once on the wire is
+/// merged into the value decoded so far. Groups are also passed their tag, to
+/// find the key that ends them.
+fn decode_message(field: &MessageField) -> TokenStream {
+    let name = field_ident(&field.name);
+    let module = message_module(field);
+    let tag = field.group.then(|| {
+        let tag = field.tag;
+        quote!(#tag,)
+    });
+    match field.label {
+        Some(Label::Repeated) => quote! {
+            #module::decode_repeated(#tag wire_type, &mut self.#name, buf)
+        },
+        _ => quote! {
+            #module::decode(
+                #tag
+                wire_type,
+                self.#name.get_or_insert_with(::std::default::Default::default),
+                buf,
+            )
+        },
+    }
+}
+
+/// The `aproto::encoding` module that encodes a message field.
+fn message_module(field: &MessageField) -> TokenStream {
+    if field.group {
+        quote!(::aproto::encoding::group)
+    } else {
+        quote!(::aproto::encoding::message)
+    }
+}
+
+fn encode_map(field: &MapField) -> TokenStream {
+    let name = field_ident(&field.name);
+    let key = ty_module(&field.key_ty);
+    let value = map_value_module(&field.value_ty);
+    let tag = field.tag;
+    quote! {
+        ::aproto::encoding::map::encode(
+            #tag,
+            &self.#name,
+            buf,
+            |tag, key, buf| #key::encode(tag, key, buf),
+            |tag, key| #key::encode_len(tag, key),
+            |tag, value, buf| #value::encode(tag, value, buf),
+            |tag, value| #value::encode_len(tag, value),
+        );
+    }
+}
+
+fn encoded_len_map(field: &MapField) -> TokenStream {
+    let name = field_ident(&field.name);
+    let key = ty_module(&field.key_ty);
+    let value = map_value_module(&field.value_ty);
+    let tag = field.tag;
+    quote! {
+        ::aproto::encoding::map::encode_len(
+            #tag,
+            &self.#name,
+            |tag, key| #key::encode_len(tag, key),
+            |tag, value| #value::encode_len(tag, value),
+        )
+    }
+}
+
+fn decode_map(field: &MapField) -> TokenStream {
+    let name = field_ident(&field.name);
+    let key = ty_module(&field.key_ty);
+    let value = map_value_module(&field.value_ty);
+    quote! {
+        ::aproto::encoding::map::decode(
+            wire_type,
+            &mut self.#name,
+            buf,
+            |wire_type, key, buf| #key::decode(wire_type, key, buf),
+            |wire_type, value, buf| #value::decode(wire_type, value, buf),
+        )
+    }
+}
+
+/// The `aproto::encoding` module that encodes a map value.
+fn map_value_module(ty: &ValueTy) -> TokenStream {
+    match ty {
+        ValueTy::Scalar(ty) => ty_module(ty),
+        ValueTy::Message(..) => quote!(::aproto::encoding::message),
+    }
+}
+
+/// The `aproto::encoding` module that encodes the field's scalar type.
+fn encoding_module(field: &ScalarField) -> TokenStream {
+    ty_module(&field.ty)
+}
+
+pub fn ty_module(ty: &scalar::Ty) -> TokenStream {
+    let module = syn::Ident::new(ty.as_str(), Span::call_site());
+    quote!(::aproto::encoding::#module)
+}
+
+fn scalar_type(field: &ScalarField) -> TokenStream {
+    let ty = field.ty.rust_type();
+    match field.label {
+        Some(Label::Repeated) => quote!(::std::vec::Vec<#ty>),
+        _ if has_presence(field.label.as_ref()) => quote!(::std::option::Option<#ty>),
+        _ => ty,
+    }
+}
+
+/// A singular field of the enclosing message's own type is boxed, otherwise
+/// the struct would have infinite size.
+fn message_type(scope: &Scope, field: &MessageField) -> TokenStream {
+    let ty = scope.struct_path(&field.ty.name);
+    match field.label {
+        Some(Label::Repeated) => quote!(::std::vec::Vec<#ty>),
+        _ if scope.is_recursive(&field.ty.name) => {
+            quote!(::std::option::Option<::std::boxed::Box<#ty>>)
+        }
+        _ => quote!(::std::option::Option<#ty>),
+    }
+}
+
+fn map_type(scope: &Scope, field: &MapField) -> TokenStream {
+    let key = field.key_ty.rust_type();
+    let value = match &field.value_ty {
+        ValueTy::Scalar(ty) => ty.rust_type(),
+        ValueTy::Message(ty) => scope.struct_path(&ty.name),
+    };
+    quote!(::std::collections::HashMap<#key, #value>)
+}
+
+/// The Rust name of a field, which is a raw identifier when the field's
+/// name is a Rust keyword, such as `r#type`.
+pub fn field_ident(name: &str) -> syn::Ident {
+    rust_ident(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use aproto_types::file::ProtobufFileDescriptor;
+    use quote::quote;
+
+    use crate::file;
+
+    #[test]
+    fn test_deprecated_fields() {
+        let file = syn::parse2::<ProtobufFileDescriptor>(quote!(
+            message Legacy {
+                string old_name = 1 [deprecated = true];
+                string new_name = 2 [deprecated = false];
+                oneof choice {
+                    int32 old_choice = 3 [deprecated = true];
+                    int32 new_choice = 4;
+                }
+            }
+        ))
+        .unwrap();
+        let generated = file::generate(&file).unwrap().to_string();
+        assert!(generated.contains("# [deprecated] pub old_name :"));
+        assert!(generated.contains("# [deprecated] OldChoice ("));
+        assert!(!generated.contains("# [deprecated] pub new_name"));
+        assert!(!generated.contains("# [deprecated] NewChoice"));
+    }
+}
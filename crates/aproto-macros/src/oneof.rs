@@ -0,0 +1,219 @@
+use aproto_types::{oneof::OneofField, Field};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::{
+    message::{check_message, field_ident, merge_arm, ty_module},
+    scope::Scope,
+    utils::{rust_ident, upper_camel_case},
+};
+
+/// A single member of a oneof, as seen by the generated enum.
+struct Member {
+    name: String,
+    variant: syn::Ident,
+    tag: u32,
+    module: TokenStream,
+    ty: TokenStream,
+    deprecated: bool,
+}
+
+/// Generates the enum holding the value of a oneof. It lives in the module
+/// named after the enclosing message, next to the message's nested types.
+pub fn generate(scope: &Scope, oneof: &OneofField) -> TokenStream {
+    let name = enum_ident(oneof);
+    let variants = members(scope, oneof).map(|member| {
+        let Member {
+            variant,
+            ty,
+            deprecated,
+            ..
+        } = member;
+        let deprecated = deprecated.then(|| quote!(#[deprecated]));
+        quote!(#deprecated #variant(#ty),)
+    });
+    quote! {
+        #[derive(Clone, Debug, PartialEq)]
+        pub enum #name {
+            #(#variants)*
+        }
+    }
+}
+
+/// A oneof is stored as an `Option` of its enum, `None` when no member is set.
+pub fn struct_type(scope: &Scope, oneof: &OneofField) -> TokenStream {
+    let path = enum_path(scope, oneof);
+    quote!(::std::option::Option<#path>)
+}
+
+/// The member that is set is always encoded, even if it holds a default value.
+pub fn encode(scope: &Scope, oneof: &OneofField) -> TokenStream {
+    let name = field_ident(&oneof.name);
+    let path = enum_path(scope, oneof);
+    let arms = members(scope, oneof).map(|member| {
+        let (variant, tag, module) = (&member.variant, member.tag, &member.module);
+        quote!(#path::#variant(value) => #module::encode(#tag, value, buf),)
+    });
+    quote! {
+        if let ::std::option::Option::Some(oneof) = &self.#name {
+            match oneof {
+                #(#arms)*
+            }
+        }
+    }
+}
+
+pub fn encoded_len(scope: &Scope, oneof: &OneofField) -> TokenStream {
+    let name = field_ident(&oneof.name);
+    let path = enum_path(scope, oneof);
+    let arms = members(scope, oneof).map(|member| {
+        let (variant, tag, module) = (&member.variant, member.tag, &member.module);
+        quote!(#path::#variant(value) => #module::encode_len(#tag, value),)
+    });
+    quote! {
+        self.#name.as_ref().map_or(0, |oneof| match oneof {
+            #(#arms)*
+        })
+    }
+}
+
+/// Each member gets its own arm. When a different member than the one already
+/// set is decoded it replaces it, while a message member that appears again
+/// is merged into the value decoded so far.
+pub fn merge(scope: &Scope, oneof: &OneofField) -> TokenStream {
+    let name = field_ident(&oneof.name);
+    let path = enum_path(scope, oneof);
+    let message = scope.full_name();
+    members(scope, oneof)
+        .map(|member| {
+            let (variant, tag, module) = (&member.variant, member.tag, &member.module);
+            let decode = quote! {
+                match &mut self.#name {
+                    ::std::option::Option::Some(#path::#variant(value)) => {
+                        #module::decode(wire_type, value, buf)
+                    }
+                    _ => {
+                        let mut value = ::std::default::Default::default();
+                        #module::decode(wire_type, &mut value, buf).map(|()| {
+                            self.#name = ::std::option::Option::Some(#path::#variant(value));
+                        })
+                    }
+                }
+            };
+            merge_arm(&message, &member.name, tag, decode)
+        })
+        .collect()
+}
+
+/// Checks the required fields of the message held by the member that is set.
+pub fn check_initialized(scope: &Scope, oneof: &OneofField) -> TokenStream {
+    let name = field_ident(&oneof.name);
+    let path = enum_path(scope, oneof);
+    let message = scope.full_name();
+    let arms = oneof
+        .fields
+        .iter()
+        .filter_map(|field| match field {
+            Field::Message(field) => Some(field),
+            _ => None,
+        })
+        .map(|field| {
+            let variant = variant_ident(&field.name);
+            let check = check_message(&message, &field.name);
+            quote!(::std::option::Option::Some(#path::#variant(value)) => { #check })
+        })
+        .collect::<Vec<_>>();
+    if arms.is_empty() {
+        return TokenStream::new();
+    }
+    quote! {
+        match &self.#name {
+            #(#arms)*
+            _ => {}
+        }
+    }
+}
+
+fn members<'a>(scope: &'a Scope, oneof: &'a OneofField) -> impl Iterator<Item = Member> + 'a {
+    oneof.fields.iter().map(move |field| match field {
+        Field::Scalar(field) => Member {
+            name: field.name.clone(),
+            variant: variant_ident(&field.name),
+            tag: field.tag,
+            module: ty_module(&field.ty),
+            ty: field.ty.rust_type(),
+            deprecated: field.options.deprecated,
+        },
+        Field::Message(field) => {
+            let ty = scope.nested_path(&field.ty.name);
+            Member {
+                name: field.name.clone(),
+                variant: variant_ident(&field.name),
+                tag: field.tag,
+                module: quote!(::aproto::encoding::message),
+                ty: if scope.is_recursive(&field.ty.name) {
+                    quote!(::std::boxed::Box<#ty>)
+                } else {
+                    ty
+                },
+                deprecated: field.options.deprecated,
+            }
+        }
+        _ => unreachable!("oneof members are scalar or message fields"),
+    })
+}
+
+fn enum_path(scope: &Scope, oneof: &OneofField) -> TokenStream {
+    let module = scope.nested_module();
+    let name = enum_ident(oneof);
+    quote!(#module::#name)
+}
+
+pub fn enum_ident(oneof: &OneofField) -> syn::Ident {
+    variant_ident(&oneof.name)
+}
+
+fn variant_ident(name: &str) -> syn::Ident {
+    rust_ident(&upper_camel_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use aproto_types::file::ProtobufFileDescriptor;
+    use quote::quote;
+
+    use crate::file;
+
+    #[test]
+    fn test_oneof_enum_name_clashes() {
+        let cases = [
+            quote!(
+                message Shape {
+                    oneof kind {
+                        int32 circle = 1;
+                    }
+                    enum Kind {
+                        UNKNOWN = 0;
+                    }
+                }
+            ),
+            quote!(
+                message Shape {
+                    oneof kind {
+                        int32 circle = 1;
+                    }
+                    oneof Kind {
+                        int32 square = 2;
+                    }
+                }
+            ),
+        ];
+        for input in cases {
+            let file = syn::parse2::<ProtobufFileDescriptor>(input).unwrap();
+            let err = file::generate(&file).unwrap_err();
+            assert!(err.to_string().ends_with(
+                "generates the enum `Kind`, which clashes with another type in `Shape`"
+            ));
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use aproto_types::{
+    enums::ProtobufEnumDescriptor,
+    file::{ProtobufFileDescriptor, Syntax},
+    resolve::{ResolvedType, Resolver, TypeKind},
+    ProtobufMessageDescriptor,
+};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::utils::{rust_ident, snake_case};
+
+/// The message being generated, together with the messages enclosing it and
+/// the file declaring them.
+///
+/// Types declared inside a message are generated in a module named after it,
+/// so `Outer.Inner` becomes `outer::Inner`.
+#[derive(Clone)]
+pub struct Scope<'a> {
+    resolver: Resolver<'a>,
+}
+
+impl<'a> Scope<'a> {
+    pub fn new(file: &'a ProtobufFileDescriptor, message: &'a ProtobufMessageDescriptor) -> Self {
+        Self {
+            resolver: Resolver::new(file).nested(message),
+        }
+    }
+
+    /// The scope of a message declared inside the current one.
+    pub fn nested(&self, message: &'a ProtobufMessageDescriptor) -> Self {
+        Self {
+            resolver: self.resolver.nested(message),
+        }
+    }
+
+    pub fn message(&self) -> &'a ProtobufMessageDescriptor {
+        self.resolver.messages().last().unwrap()
+    }
+
+    /// The dotted protobuf name of the message, such as `Outer.Inner`.
+    pub fn full_name(&self) -> String {
+        self.resolver
+            .messages()
+            .iter()
+            .map(|message| message.name.as_str())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// Resolves a type name against the file. `message!` only sees a single
+    /// message, so names it does not declare are taken to be messages next to
+    /// the macro invocation, with a dotted name such as `Outer.Inner` naming
+    /// `outer::Inner`.
+    pub fn resolve(&self, name: &str) -> ResolvedType {
+        self.resolver.resolve(name).unwrap_or_else(|| {
+            let mut parents = name
+                .trim_start_matches('.')
+                .split('.')
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            ResolvedType {
+                name: parents.pop().unwrap(),
+                parents,
+                kind: TypeKind::Message,
+            }
+        })
+    }
+
+    pub fn syntax(&self) -> Syntax {
+        self.resolver.file().syntax
+    }
+
+    /// The enum a type name refers to, if it is declared in the file.
+    pub fn resolve_enum(&self, name: &str) -> Option<&'a ProtobufEnumDescriptor> {
+        self.resolver.resolve_enum(name)
+    }
+
+    pub fn is_enum(&self, name: &str) -> bool {
+        self.resolve(name).kind == TypeKind::Enum
+    }
+
+    /// Whether a field of the type must be boxed, as a value of the type can
+    /// contain the message being generated.
+    pub fn is_recursive(&self, name: &str) -> bool {
+        self.resolver.is_recursive(name)
+    }
+
+    /// The path to a type, as written next to the message's struct.
+    pub fn struct_path(&self, name: &str) -> TokenStream {
+        let messages = self.resolver.messages();
+        path_from(&self.resolve(name), &messages[..messages.len() - 1])
+    }
+
+    /// The path to a type, as written inside the module holding the types
+    /// nested in the message.
+    pub fn nested_path(&self, name: &str) -> TokenStream {
+        path_from(&self.resolve(name), self.resolver.messages())
+    }
+
+    /// The module holding the types nested in the message, relative to the
+    /// message's struct.
+    pub fn nested_module(&self) -> syn::Ident {
+        module_ident(&self.message().name)
+    }
+}
+
+/// A path to a resolved type, relative to the module holding the types nested
+/// in the given messages.
+fn path_from(resolved: &ResolvedType, messages: &[&ProtobufMessageDescriptor]) -> TokenStream {
+    let common = resolved
+        .parents
+        .iter()
+        .zip(messages)
+        .take_while(|(parent, message)| **parent == message.name)
+        .count();
+    let supers = (common..messages.len()).map(|_| quote!(super::));
+    let modules = resolved.parents[common..]
+        .iter()
+        .map(|parent| module_ident(parent));
+    let name = syn::Ident::new(&resolved.name, Span::call_site());
+    quote!(#(#supers)* #(#modules::)* #name)
+}
+
+fn module_ident(message: &str) -> syn::Ident {
+    rust_ident(&snake_case(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::quote;
+
+    #[test]
+    fn test_resolve() {
+        let file = syn::parse2::<ProtobufFileDescriptor>(quote!(
+            message Outer {
+                message Inner {
+                    message Leaf {}
+                }
+                enum Kind {
+                    A = 0;
+                }
+            }
+            enum Status {
+                UNKNOWN = 0;
+            }
+        ))
+        .unwrap();
+        let outer = &file.messages[0];
+        let outer_scope = Scope::new(&file, outer);
+        let inner_scope = outer_scope.nested(&outer.messages[0]);
+
+        assert_eq!(
+            outer_scope.struct_path("Inner").to_string(),
+            "outer :: Inner"
+        );
+        assert_eq!(outer_scope.nested_path("Inner").to_string(), "Inner");
+        assert_eq!(outer_scope.struct_path("Other").to_string(), "Other");
+        assert_eq!(
+            outer_scope.nested_path("Other").to_string(),
+            "super :: Other"
+        );
+
+        assert_eq!(inner_scope.struct_path("Kind").to_string(), "Kind");
+        assert!(inner_scope.is_enum("Kind"));
+        assert_eq!(inner_scope.struct_path("Leaf").to_string(), "inner :: Leaf");
+        assert_eq!(
+            inner_scope.struct_path("Outer").to_string(),
+            "super :: Outer"
+        );
+        assert_eq!(inner_scope.nested_path("Kind").to_string(), "super :: Kind");
+
+        assert_eq!(
+            inner_scope.struct_path("Status").to_string(),
+            "super :: Status"
+        );
+        assert!(inner_scope.is_enum("Status"));
+        assert!(!inner_scope.is_enum("Outer"));
+        assert!(!inner_scope.is_enum("Other"));
+
+        assert!(inner_scope.is_recursive("Inner"));
+        assert!(!inner_scope.is_recursive("Outer"));
+        assert!(outer_scope.is_recursive("Outer"));
+
+        assert_eq!(
+            inner_scope.struct_path("Outer.Inner.Leaf").to_string(),
+            "inner :: Leaf"
+        );
+        assert!(inner_scope.is_recursive(".Outer.Inner"));
+        assert_eq!(
+            inner_scope.struct_path("Other.Nested").to_string(),
+            "super :: other :: Nested"
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use aproto_types::utils::is_rust_reserve_key_word;
+use proc_macro2::Span;
+use quote::format_ident;
+
+/// Converts a `snake_case` or `UPPER_SNAKE_CASE` protobuf name to an
+/// `UpperCamelCase` Rust name.
+pub fn upper_camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            std::iter::once(first)
+                .chain(chars.map(|c| c.to_ascii_lowercase()))
+                .collect::<String>()
+        })
+        .collect()
+}
+
+/// Converts an `UpperCamelCase` protobuf name to a `snake_case` Rust name.
+pub fn snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut snake = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|c| c.is_ascii_lowercase());
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_is_lower)
+            {
+                snake.push('_');
+            }
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// A Rust identifier for a generated name. Keywords become raw identifiers,
+/// except those that cannot be raw, which get a trailing underscore.
+pub fn rust_ident(name: &str) -> syn::Ident {
+    match name {
+        "self" | "Self" | "super" | "crate" => format_ident!("{name}_"),
+        name if is_rust_reserve_key_word(name) => syn::Ident::new_raw(name, Span::call_site()),
+        name => syn::Ident::new(name, Span::call_site()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upper_camel_case() {
+        assert_eq!(upper_camel_case("UNKNOWN"), "Unknown");
+        assert_eq!(upper_camel_case("STATUS_ACTIVE"), "StatusActive");
+        assert_eq!(upper_camel_case("Already_Camel"), "AlreadyCamel");
+        assert_eq!(upper_camel_case("V2_BETA"), "V2Beta");
+        assert_eq!(upper_camel_case("payload"), "Payload");
+        assert_eq!(upper_camel_case("blob_data"), "BlobData");
+    }
+
+    #[test]
+    fn test_snake_case() {
+        assert_eq!(snake_case("Person"), "person");
+        assert_eq!(snake_case("TestMessage"), "test_message");
+        assert_eq!(snake_case("HTTPRequest"), "http_request");
+        assert_eq!(snake_case("Version2Info"), "version2_info");
+        assert_eq!(snake_case("snake_case"), "snake_case");
+    }
+
+    #[test]
+    fn test_rust_ident() {
+        assert_eq!(rust_ident("name").to_string(), "name");
+        assert_eq!(rust_ident("type").to_string(), "r#type");
+        assert_eq!(rust_ident("match").to_string(), "r#match");
+        assert_eq!(rust_ident("self").to_string(), "self_");
+        assert_eq!(rust_ident("Self").to_string(), "Self_");
+    }
+}
@@ -0,0 +1,253 @@
+use syn::parse::{Parse, ParseStream};
+
+use crate::{
+    fields::{
+        reserved::Reserved,
+        utils::{
+            is_protobuf_reserve_key_word, parse_int, parse_option, parse_statements, peek_keyword,
+            peek_option,
+        },
+    },
+    file::Syntax,
+};
+
+/// A protobuf enum definition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProtobufEnumDescriptor {
+    pub name: String,
+    pub values: Vec<EnumValue>,
+    /// The values and names reserved by `reserved` statements.
+    pub reserved: Reserved<i32>,
+}
+
+/// A single named value of a protobuf enum.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnumValue {
+    pub name: String,
+    pub number: i32,
+}
+
+impl Parse for ProtobufEnumDescriptor {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Self::parse_with_syntax(input, Syntax::Proto3)
+    }
+}
+
+impl ProtobufEnumDescriptor {
+    /// The value of a field of the enum's type that is not set: the first
+    /// value.
+    pub fn default_value(&self) -> &EnumValue {
+        &self.values[0]
+    }
+
+    /// The value with the given name.
+    pub fn value(&self, name: &str) -> Option<&EnumValue> {
+        self.values.iter().find(|value| value.name == name)
+    }
+
+    /// Parses an enum written in the given syntax.
+    pub fn parse_with_syntax(input: ParseStream, syntax: Syntax) -> syn::Result<Self> {
+        input.parse::<syn::Token![enum]>()?;
+        let name = input.parse::<syn::Ident>()?;
+        if is_protobuf_reserve_key_word(&name.to_string()) {
+            return Err(syn::Error::new(name.span(), "reserved keyword"));
+        }
+
+        let content;
+        syn::braced!(content in input);
+        let mut values: Vec<EnumValue> = Vec::new();
+        let mut reserved = Reserved::default();
+        parse_statements(&content, |content| {
+            if peek_option(content) {
+                return parse_option(content);
+            }
+            if peek_keyword(content, "reserved") {
+                return reserved.parse_statement(content, |reserved| {
+                    values
+                        .iter()
+                        .find_map(|value| reserved_conflict(value, reserved))
+                });
+            }
+            let value_name = content.parse::<syn::Ident>()?;
+            content.parse::<syn::Token![=]>()?;
+            let number_lit = content.parse::<syn::LitInt>()?;
+            let number = parse_int::<i32>(&number_lit)?;
+            content.parse::<syn::Token![;]>()?;
+
+            if values.iter().any(|value| value_name == value.name) {
+                return Err(syn::Error::new(
+                    value_name.span(),
+                    "duplicate enum value name",
+                ));
+            }
+            if values.iter().any(|value| value.number == number) {
+                return Err(syn::Error::new(
+                    number_lit.span(),
+                    "duplicate enum value number, aliases are not supported",
+                ));
+            }
+
+            let value = EnumValue {
+                name: value_name.to_string(),
+                number,
+            };
+            if let Some(message) = reserved_conflict(&value, &reserved) {
+                return Err(syn::Error::new(value_name.span(), message));
+            }
+            values.push(value);
+            Ok(())
+        })?;
+
+        // The first value is the default, which proto3 requires to be zero
+        match values.first() {
+            None => Err(syn::Error::new(
+                name.span(),
+                "enum must have at least one value",
+            )),
+            Some(first) if first.number != 0 && syntax == Syntax::Proto3 => Err(syn::Error::new(
+                name.span(),
+                "the first enum value must be zero",
+            )),
+            Some(_) => Ok(Self {
+                name: name.to_string(),
+                values,
+                reserved,
+            }),
+        }
+    }
+}
+
+/// Describes how an enum value uses a reserved number or name.
+fn reserved_conflict(value: &EnumValue, reserved: &Reserved<i32>) -> Option<String> {
+    let EnumValue { name, number } = value;
+    if reserved.contains_name(name) {
+        Some(format!("enum value name `{name}` is reserved"))
+    } else if reserved.contains_number(*number) {
+        Some(format!(
+            "enum value {number} is reserved but used by `{name}`"
+        ))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::quote;
+
+    #[test]
+    fn test_parse_enum_descriptor() {
+        let input = quote!(
+            enum Status {
+                UNKNOWN = 0;
+                ACTIVE = 1;
+                DELETED = -1;
+            }
+        );
+        let descriptor = syn::parse2::<ProtobufEnumDescriptor>(input).unwrap();
+        assert_eq!(descriptor.name, "Status");
+        assert_eq!(
+            descriptor.values,
+            vec![
+                EnumValue {
+                    name: "UNKNOWN".to_string(),
+                    number: 0
+                },
+                EnumValue {
+                    name: "ACTIVE".to_string(),
+                    number: 1
+                },
+                EnumValue {
+                    name: "DELETED".to_string(),
+                    number: -1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_enum_descriptor() {
+        for input in [
+            quote!(
+                enum Status {}
+            ),
+            quote!(enum Status { ACTIVE = 1; }),
+            quote!(enum Status { UNKNOWN = 0; UNKNOWN = 1; }),
+            quote!(enum Status { UNKNOWN = 0; OTHER = 0; }),
+            quote!(enum Status { UNKNOWN = 0; BIG = 2147483648; }),
+            quote!(enum message { UNKNOWN = 0; }),
+        ] {
+            assert!(syn::parse2::<ProtobufEnumDescriptor>(input).is_err());
+        }
+    }
+
+    #[test]
+    fn test_reserved() {
+        let input = quote!(
+            enum Status {
+                reserved 2, 15, 9 to 11, -5 to -1;
+                reserved "DELETED";
+                UNKNOWN = 0;
+                ACTIVE = 1;
+                reserved 100 to max;
+            }
+        );
+        let descriptor = syn::parse2::<ProtobufEnumDescriptor>(input).unwrap();
+        assert_eq!(descriptor.values.len(), 2);
+        assert_eq!(
+            descriptor.reserved.ranges,
+            [2..=2, 15..=15, 9..=11, -5..=-1, 100..=i32::MAX]
+        );
+        assert_eq!(descriptor.reserved.names, ["DELETED"]);
+
+        let cases = [
+            (
+                quote!(enum Status { reserved 1 to 3; UNKNOWN = 0; ACTIVE = 2; }),
+                "enum value 2 is reserved but used by `ACTIVE`",
+            ),
+            (
+                quote!(enum Status { UNKNOWN = 0; OLD = -2; reserved -3 to -1; }),
+                "enum value -2 is reserved but used by `OLD`",
+            ),
+            (
+                quote!(enum Status { UNKNOWN = 0; reserved "UNKNOWN"; }),
+                "enum value name `UNKNOWN` is reserved",
+            ),
+            (
+                quote!(enum Status { UNKNOWN = 0; reserved 2147483648; }),
+                "reserved enum value must be between -2147483648 and 2147483647",
+            ),
+        ];
+        for (input, expected) in cases {
+            let err = syn::parse2::<ProtobufEnumDescriptor>(input).err().unwrap();
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_value_errors_are_aggregated() {
+        let input = quote!(
+            enum Status {
+                UNKNOWN = 0;
+                ACTIVE = 0;
+                DONE 2;
+                UNKNOWN = 3;
+            }
+        );
+        let errors = syn::parse2::<ProtobufEnumDescriptor>(input)
+            .err()
+            .unwrap()
+            .into_iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "duplicate enum value number, aliases are not supported",
+                "expected `=`",
+                "duplicate enum value name",
+            ]
+        );
+    }
+}
@@ -2,4 +2,37 @@
 pub enum AprotoError {
     #[error("invalid wire type: {0}")]
     InvalidWireType(u64),
+    #[error("invalid field number: {0}")]
+    InvalidTag(u64),
+    #[error("unexpected wire type: expected {expected}, found {actual}")]
+    UnexpectedWireType { expected: u8, actual: u8 },
+    #[error("varint truncated: buffer ended after {0} bytes")]
+    VarintTruncated(usize),
+    #[error("varint overflow: more than 64 bits")]
+    VarintOverflow,
+    #[error("unexpected end group for field {0}")]
+    UnexpectedEndGroup(u32),
+    #[error("invalid utf-8 string")]
+    InvalidUtf8,
+    #[error("value exceeds its delimited length")]
+    DelimitedLengthExceeded,
+    #[error("buffer underflow")]
+    BufferUnderflow,
+    #[error("insufficient buffer capacity: required {required}, remaining {remaining}")]
+    InsufficientBuffer { required: usize, remaining: usize },
+    #[error("unknown {enumeration} value: {value}")]
+    UnknownEnumValue {
+        enumeration: &'static str,
+        value: i32,
+    },
+    #[error("recursion limit exceeded")]
+    RecursionLimitExceeded,
+    #[error("missing required field")]
+    MissingRequiredField,
+    #[error("{message}.{field}: {source}")]
+    Field {
+        message: &'static str,
+        field: &'static str,
+        source: Box<AprotoError>,
+    },
 }
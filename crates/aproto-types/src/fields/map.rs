@@ -1,6 +1,9 @@
-use syn::parse::{Parse, ParseStream};
+use syn::{
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+};
 
-use super::scalar;
+use super::{options::FieldOptions, scalar, type_ref::TypeRef, utils::parse_tag};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct MapField {
@@ -8,26 +11,29 @@ pub struct MapField {
     pub key_ty: scalar::Ty,
     pub value_ty: ValueTy,
     pub tag: u32,
+    pub options: FieldOptions,
 }
 
 #[allow(unused)]
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ValueTy {
     Scalar(scalar::Ty),
-    Message(String),
+    Message(TypeRef),
 }
 
 impl Parse for ValueTy {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let fork = input.fork();
+        if fork.peek(syn::Token![.]) {
+            return Ok(ValueTy::Message(input.parse()?));
+        }
         if fork.peek(syn::Ident) {
             if let Ok(ty) = fork.parse::<scalar::Ty>() {
                 input.parse::<syn::Ident>()?;
                 return Ok(ValueTy::Scalar(ty));
             }
 
-            let ident = input.parse::<syn::Ident>()?;
-            return Ok(ValueTy::Message(ident.to_string()));
+            return Ok(ValueTy::Message(input.parse()?));
         }
         Err(syn::Error::new(
             input.span(),
@@ -52,23 +58,42 @@ impl Parse for MapField {
             input.parse::<syn::Ident>()?;
             input.parse::<syn::Token![<]>()?;
 
+            let key_span = input.span();
             let key_ty = input.parse::<scalar::Ty>()?;
+            if !key_ty.is_valid_map_key() {
+                return Err(syn::Error::new(
+                    key_span,
+                    format!(
+                        "`{key_ty}` cannot be a map key, expected an integer, bool or string type"
+                    ),
+                ));
+            }
             input.parse::<syn::Token![,]>()?;
 
             let value_ty = input.parse::<ValueTy>()?;
             input.parse::<syn::Token![>]>()?;
 
-            let name = input.parse::<syn::Ident>()?;
+            let name = input.call(syn::Ident::parse_any)?;
             input.parse::<syn::Token![=]>()?;
 
-            let tag = input.parse::<syn::LitInt>()?;
+            let tag = parse_tag(input)?;
+            let options_span = input.span();
+            let options = input.parse::<FieldOptions>()?;
+            options.check_packable(options_span, false)?;
+            if options.default.is_some() {
+                return Err(syn::Error::new(
+                    options_span,
+                    "map fields cannot have default values",
+                ));
+            }
             input.parse::<syn::Token![;]>()?;
 
             return Ok(MapField {
                 name: name.to_string(),
                 key_ty,
                 value_ty,
-                tag: tag.base10_parse::<u32>().unwrap(),
+                tag,
+                options,
             });
         }
 
@@ -104,7 +129,7 @@ mod tests {
             ],
         ) {
             let name_ident = syn::parse_str::<syn::Ident>(&name).unwrap();
-            let key_ident = syn::parse_str::<syn::Ident>(&key_type).unwrap();
+            let key_ident = syn::parse_str::<syn::Ident>(key_type).unwrap();
             let value_ident = syn::parse_str::<syn::Ident>(&value_type.0).unwrap();
 
             let input = quote!(
@@ -126,11 +151,19 @@ mod tests {
                 );
             } else {
                 // Message type
-                prop_assert_eq!(
-                    field.value_ty,
-                    ValueTy::Message(value_type.0.to_string())
+                prop_assert!(
+                    matches!(field.value_ty, ValueTy::Message(ty) if ty.name == value_type.0)
                 );
             }
         }
     }
+
+    #[test]
+    fn test_invalid_map_key() {
+        for key in ["float", "double", "bytes"] {
+            let key = syn::parse_str::<syn::Ident>(key).unwrap();
+            let input = quote!(map<#key, string> values = 1;);
+            assert!(syn::parse2::<MapField>(input).is_err());
+        }
+    }
 }
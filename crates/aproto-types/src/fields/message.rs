@@ -1,42 +1,56 @@
-use syn::parse::{Parse, ParseStream};
+use syn::{
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+};
 
-use super::Label;
+use super::{
+    options::FieldOptions,
+    type_ref::TypeRef,
+    utils::{parse_label, parse_tag},
+    Label,
+};
 
 #[allow(unused)]
 #[derive(Clone)]
 pub struct MessageField {
     pub name: String,
-    pub ty: String,
+    pub ty: TypeRef,
     pub label: Option<Label>,
     pub tag: u32,
+    pub options: FieldOptions,
+    /// Whether the field is a proto2 group, whose value is encoded between
+    /// `StartGroup` and `EndGroup` keys rather than length-delimited.
+    pub group: bool,
 }
 
 impl Parse for MessageField {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let fork = input.fork();
-
-        if fork.peek(syn::Ident) {
-            let ident = fork.parse::<syn::Ident>()?;
-            let label = Label::from_str(ident.to_string().as_str());
-            if label.is_some() {
-                input.parse::<syn::Ident>()?;
-            }
-
-            let ty = input.parse::<syn::Ident>()?;
-            let name = input.parse::<syn::Ident>()?;
-            input.parse::<syn::Token![=]>()?;
-            let tag = input.parse::<syn::LitInt>()?.base10_parse::<u32>()?;
-            input.parse::<syn::Token![;]>()?;
-
-            return Ok(Self {
-                name: name.to_string(),
-                ty: ty.to_string(),
-                label,
-                tag,
-            });
+        let label = parse_label(input)?;
+        let ty = input.parse::<TypeRef>()?;
+        let name = input.call(syn::Ident::parse_any)?;
+        input.parse::<syn::Token![=]>()?;
+        let tag = parse_tag(input)?;
+        let options_span = input.span();
+        let options = input.parse::<FieldOptions>()?;
+        // Repeated enum fields are packable, which is only known once the
+        // type is resolved, so the rest is checked then
+        options.check_packable(options_span, label == Some(Label::Repeated))?;
+        if label == Some(Label::Repeated) && options.default.is_some() {
+            return Err(syn::Error::new(
+                options_span,
+                "repeated fields cannot have default values",
+            ));
         }
+        input.parse::<syn::Token![;]>()?;
 
-        Err(syn::Error::new(input.span(), "expected message field"))
+        Ok(Self {
+            name: name.to_string(),
+            ty,
+            label,
+            tag,
+            options,
+            group: false,
+        })
     }
 }
 
@@ -76,9 +90,21 @@ mod tests {
 
             let field = syn::parse2::<MessageField>(input).unwrap();
             assert_eq!(field.name, name);
-            assert_eq!(field.ty, ty);
+            assert_eq!(field.ty.name, ty);
             assert_eq!(field.label, expected_label);
             assert_eq!(field.tag, tag);
         }
     }
+
+    #[test]
+    fn test_parse_dotted_message_field() {
+        let field =
+            syn::parse2::<MessageField>(quote!(repeated .foo.Outer.Inner inner = 1;)).unwrap();
+        assert_eq!(field.ty.name, ".foo.Outer.Inner");
+        assert_eq!(field.label, Some(Label::Repeated));
+
+        let field = syn::parse2::<MessageField>(quote!(Outer.Inner inner = 2;)).unwrap();
+        assert_eq!(field.ty.name, "Outer.Inner");
+        assert_eq!(field.label, None);
+    }
 }
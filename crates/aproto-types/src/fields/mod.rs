@@ -1,12 +1,26 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, ops::RangeInclusive};
 
+use proc_macro2::Span;
 use syn::parse::{Parse, ParseStream};
 
-mod map;
-mod message;
-mod scalar;
+use crate::file::Syntax;
+
+pub mod map;
+pub mod message;
+pub mod oneof;
+pub mod options;
+pub mod reserved;
+pub mod scalar;
+pub mod type_ref;
 pub mod utils;
 
+/// The smallest valid field number.
+pub const MIN_TAG: u32 = 1;
+/// The largest valid field number, `2^29 - 1`.
+pub const MAX_TAG: u32 = (1 << 29) - 1;
+/// Field numbers reserved for the protobuf implementation.
+pub const RESERVED_TAGS: RangeInclusive<u32> = 19000..=19999;
+
 #[allow(unused)]
 #[derive(Clone)]
 pub enum Field {
@@ -16,42 +30,199 @@ pub enum Field {
     Message(message::MessageField),
     /// A map protobuf field.
     Map(map::MapField),
+    /// A oneof protobuf field.
+    Oneof(oneof::OneofField),
 }
 
-#[allow(unused)]
-pub struct Fields(pub Vec<Field>);
+impl Field {
+    pub fn name(&self) -> &str {
+        match self {
+            Field::Scalar(field) => &field.name,
+            Field::Message(field) => &field.name,
+            Field::Map(field) => &field.name,
+            Field::Oneof(field) => &field.name,
+        }
+    }
 
-impl Parse for Fields {
+    /// The field's options, or `None` for a oneof, whose members carry their
+    /// own.
+    pub fn options(&self) -> Option<&options::FieldOptions> {
+        match self {
+            Field::Scalar(field) => Some(&field.options),
+            Field::Message(field) => Some(&field.options),
+            Field::Map(field) => Some(&field.options),
+            Field::Oneof(..) => None,
+        }
+    }
+
+    /// The field numbers used by the field, one per member for a oneof.
+    pub fn tags(&self) -> Vec<u32> {
+        match self {
+            Field::Scalar(field) => vec![field.tag],
+            Field::Message(field) => vec![field.tag],
+            Field::Map(field) => vec![field.tag],
+            Field::Oneof(field) => field.fields.iter().flat_map(Field::tags).collect(),
+        }
+    }
+}
+
+impl Parse for Field {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let mut fields = Vec::new();
-        let mut used_tags = HashSet::new();
-
-        while !input.is_empty() {
-            let proto_field: Field;
-            if let Ok(field) = input.parse::<scalar::ScalarField>() {
-                proto_field = Field::Scalar(field);
-            } else if let Ok(field) = input.parse::<map::MapField>() {
-                proto_field = Field::Map(field);
-            } else if let Ok(field) = input.parse::<message::MessageField>() {
-                proto_field = Field::Message(field);
-            } else {
-                return Err(syn::Error::new(input.span(), "expected a protobuf field"));
-            }
+        // The kind of field is decided up front, so errors such as an invalid
+        // field number are reported as they are rather than retrying the
+        // tokens as another kind of field.
+        let keyword = input.fork().parse::<syn::Ident>();
+        if keyword.as_ref().is_ok_and(|ident| ident == "oneof") {
+            return Ok(Field::Oneof(input.parse()?));
+        }
+        if keyword.as_ref().is_ok_and(|ident| ident == "map") {
+            return Ok(Field::Map(input.parse()?));
+        }
 
-            let tag = match &proto_field {
-                Field::Scalar(field) => field.tag,
-                Field::Map(field) => field.tag,
-                Field::Message(field) => field.tag,
+        let fork = input.fork();
+        let label = utils::parse_label(&fork)?;
+        if fork.peek(syn::Token![.]) {
+            return Ok(Field::Message(input.parse()?));
+        }
+        let Ok(ty) = fork.parse::<syn::Ident>() else {
+            return Err(syn::Error::new(input.span(), "expected a protobuf field"));
+        };
+        let ty_name = ty.to_string();
+        if scalar::ScalarField::is_scalar_field(&ty_name) {
+            return Ok(Field::Scalar(input.parse()?));
+        }
+
+        // A misspelled label leaves two identifiers between it and the name,
+        // as in `repated int32 ids = 1;`
+        if label.is_none() && fork.peek(syn::Ident) && fork.peek2(syn::Ident) {
+            let message = match utils::suggest(&ty_name, ["optional", "repeated", "required"]) {
+                Some(label) => format!("unknown label `{ty}`, did you mean `{label}`?"),
+                None => format!("unknown label `{ty}`, expected `optional` or `repeated`"),
             };
+            return Err(syn::Error::new(ty.span(), message));
+        }
 
-            if used_tags.contains(&tag) {
-                return Err(syn::Error::new(input.span(), "duplicate tag"));
+        // Message and enum names are `UpperCamelCase`, so a lowercase name
+        // close to a scalar type is almost certainly a typo, unless it is the
+        // package part of a dotted name
+        if ty_name.starts_with(|c: char| c.is_ascii_lowercase()) && !fork.peek(syn::Token![.]) {
+            if let Some(scalar) = utils::suggest(&ty_name, scalar::Ty::NAMES) {
+                return Err(syn::Error::new(
+                    ty.span(),
+                    format!("unknown type `{ty}`, did you mean `{scalar}`?"),
+                ));
             }
-            used_tags.insert(tag);
+        }
+
+        Ok(Field::Message(input.parse()?))
+    }
+}
+
+#[allow(unused)]
+#[derive(Default)]
+pub struct Fields(pub Vec<Field>, reserved::Reserved);
+
+impl Fields {
+    /// The field numbers and names reserved by `reserved` statements.
+    pub fn reserved(&self) -> &reserved::Reserved {
+        &self.1
+    }
+
+    /// Adds a field or `reserved` statement parsed from `input`, see
+    /// [`Fields::add`].
+    pub fn parse_field(&mut self, input: ParseStream, syntax: Syntax) -> syn::Result<()> {
+        if utils::peek_keyword(input, "reserved") {
+            let Self(fields, reserved) = self;
+            return reserved.parse_statement(input, |reserved| {
+                fields
+                    .iter()
+                    .find_map(|field| reserved_conflict(field, reserved))
+            });
+        }
+
+        let span = input.span();
+        let field = input.parse::<Field>()?;
+        self.add(span, field, syntax)
+    }
+
+    /// Adds a field declared at `span`, rejecting a field the syntax does not
+    /// allow, a tag that is already used by another field, including the
+    /// members of oneofs, and fields that use a reserved number or name.
+    pub fn add(&mut self, span: Span, field: Field, syntax: Syntax) -> syn::Result<()> {
+        if let Some(message) = syntax_conflict(&field, syntax) {
+            return Err(syn::Error::new(span, message));
+        }
+        let mut used_tags = self.0.iter().flat_map(Field::tags).collect::<HashSet<_>>();
+        if !field.tags().into_iter().all(|tag| used_tags.insert(tag)) {
+            return Err(syn::Error::new(span, "duplicate tag"));
+        }
+        if let Some(message) = reserved_conflict(&field, &self.1) {
+            return Err(syn::Error::new(span, message));
+        }
+        self.0.push(field);
+        Ok(())
+    }
+}
+
+/// Describes how a field, or a member of a oneof, does not follow the rules of
+/// the file's syntax.
+fn syntax_conflict(field: &Field, syntax: Syntax) -> Option<String> {
+    let (label, group) = match field {
+        Field::Scalar(field) => (field.label.as_ref(), false),
+        Field::Message(field) => (field.label.as_ref(), field.group),
+        Field::Map(..) => (Some(&Label::Repeated), false),
+        // Members of a oneof are never labeled
+        Field::Oneof(oneof) if syntax == Syntax::Proto3 => {
+            return oneof
+                .fields
+                .iter()
+                .find_map(|member| syntax_conflict(member, syntax))
+        }
+        Field::Oneof(..) => return None,
+    };
+    let default = field
+        .options()
+        .is_some_and(|options| options.default.is_some());
+    let message = match syntax {
+        Syntax::Proto2 if label.is_none() => {
+            "fields must be labeled `optional`, `required` or `repeated` in proto2"
+        }
+        Syntax::Proto2 => return None,
+        Syntax::Proto3 if label == Some(&Label::Required) => {
+            "required fields are not allowed in proto3"
+        }
+        Syntax::Proto3 if group => "groups are not allowed in proto3, use a nested message",
+        Syntax::Proto3 if default => "default values are not allowed in proto3",
+        Syntax::Proto3 => return None,
+    };
+    Some(message.to_string())
+}
 
-            fields.push(proto_field);
+/// Describes how a field, or a member of a oneof, uses a reserved number or
+/// name.
+fn reserved_conflict(field: &Field, reserved: &reserved::Reserved) -> Option<String> {
+    let members = match field {
+        Field::Oneof(oneof) => oneof.fields.as_slice(),
+        field => std::slice::from_ref(field),
+    };
+    members.iter().find_map(|member| {
+        let name = member.name();
+        if reserved.contains_name(name) {
+            return Some(format!("field name `{name}` is reserved"));
         }
-        Ok(Fields(fields))
+        member
+            .tags()
+            .into_iter()
+            .find(|tag| reserved.contains_number(*tag))
+            .map(|tag| format!("field number {tag} is reserved but used by `{name}`"))
+    })
+}
+
+impl Parse for Fields {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut fields = Fields::default();
+        utils::parse_statements(input, |input| fields.parse_field(input, Syntax::Proto3))?;
+        Ok(fields)
     }
 }
 
@@ -62,6 +233,8 @@ pub enum Label {
     Optional,
     /// A repeated field.
     Repeated,
+    /// A proto2 field that must be set for a message to decode.
+    Required,
 }
 
 #[allow(clippy::should_implement_trait)]
@@ -70,6 +243,7 @@ impl Label {
         match s {
             "optional" => Some(Self::Optional),
             "repeated" => Some(Self::Repeated),
+            "required" => Some(Self::Required),
             _ => None,
         }
     }
@@ -80,7 +254,7 @@ mod tests {
     use super::*;
     use proptest::prelude::*;
     use quote::quote;
-    use utils::{is_protobuf_reserve_key_word, is_rust_reserve_key_word};
+    use utils::is_protobuf_reserve_key_word;
 
     proptest! {
         #[test]
@@ -89,13 +263,15 @@ mod tests {
 
                 let is_valid_name = |name: &str| {
                     !is_protobuf_reserve_key_word(name) &&
-                    !is_rust_reserve_key_word(name) &&
-                    name.chars().next().map_or(false, |c| c.is_ascii_alphabetic()) &&
+                    name.chars().next().is_some_and(|c| c.is_ascii_alphabetic()) &&
                     name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
                 };
 
                 let valid_name_strategy = "[a-zA-Z][a-zA-Z0-9_]*"
                     .prop_filter("filtered out reserved words", move |s| is_valid_name(&s.clone()));
+                // Lowercase type names may be taken for a misspelled scalar type
+                let type_name_strategy = "[A-Z][a-zA-Z0-9_]*"
+                    .prop_filter("filtered out reserved words", move |s| is_valid_name(&s.clone()));
 
                 (
                     Just(num_fields),
@@ -115,28 +291,39 @@ mod tests {
                         "uint64",
                         "int32",
                         "int64",
+                        "sint32",
+                        "sint64",
+                        "fixed32",
+                        "fixed64",
+                        "sfixed32",
+                        "sfixed64",
                         "bool",
                         "string",
                         "bytes",
                     ], num_fields),
-                    prop::collection::vec(valid_name_strategy, num_fields),
+                    prop::collection::vec(type_name_strategy, num_fields),
                 )
             })
         ) {
 
+            // Floating point and bytes types cannot be map keys
+            let map_key_type = |ty: &str| {
+                if scalar::Ty::from_str(ty).unwrap().is_valid_map_key() { ty.to_string() } else { "string".to_string() }
+            };
+
             let mut tokens = quote!();
             for i in 0..num_fields {
                 let field_type = &field_types[i];
-                let tag = i as u32;
+                let tag = i as u32 + 1;
                 let name = names[i].clone();
-                let label = labels[i].clone();
+                let label = labels[i];
                 let scalar_type = &scalar_types[i];
                 let message_type = &message_types[i];
 
                 match field_type.as_str() {
                     "scalar" => {
                         let scalar_type_ident = syn::parse_str::<syn::Ident>(scalar_type).unwrap();
-                        let name_ident = syn::parse_str::<syn::Ident>(&name).unwrap();
+                        let name_ident = syn::Ident::new(&name, proc_macro2::Span::call_site());
                         let input = if let Some(label_str) = &label {
                             let label_ident = syn::parse_str::<syn::Ident>(label_str).unwrap();
                             quote!(#label_ident #scalar_type_ident #name_ident = #tag;)
@@ -147,7 +334,7 @@ mod tests {
                     },
                     "message" => {
                         let message_type_ident = syn::parse_str::<syn::Ident>(message_type).unwrap();
-                        let name_ident = syn::parse_str::<syn::Ident>(&name).unwrap();
+                        let name_ident = syn::Ident::new(&name, proc_macro2::Span::call_site());
                         let input = if let Some(label_str) = &label {
                             let label_ident = syn::parse_str::<syn::Ident>(label_str).unwrap();
                             quote!(#label_ident #message_type_ident #name_ident = #tag;)
@@ -157,9 +344,10 @@ mod tests {
                         tokens.extend(input);
                     },
                     "map" => {
+                        let key_type_ident = syn::parse_str::<syn::Ident>(&map_key_type(scalar_type)).unwrap();
                         let scalar_type_ident = syn::parse_str::<syn::Ident>(scalar_type).unwrap();
-                        let name_ident = syn::parse_str::<syn::Ident>(&name).unwrap();
-                        let input = quote!(map<#scalar_type_ident, #scalar_type_ident> #name_ident = #tag;);
+                        let name_ident = syn::Ident::new(&name, proc_macro2::Span::call_site());
+                        let input = quote!(map<#key_type_ident, #scalar_type_ident> #name_ident = #tag;);
                         tokens.extend(input);
                     },
                     _ => unreachable!(),
@@ -172,7 +360,7 @@ mod tests {
             for (i, field) in fields.0.iter().enumerate() {
                 match field {
                     Field::Scalar(scalar) => {
-                        assert_eq!(scalar.tag, i as u32);
+                        assert_eq!(scalar.tag, i as u32 + 1);
                         assert_eq!(scalar.name, names[i]);
                         assert_eq!(scalar.ty, scalar::Ty::from_str(&scalar_types[i]).unwrap());
                         if let Some(label) = labels[i] {
@@ -182,22 +370,245 @@ mod tests {
                         }
                     },
                     Field::Message(message) => {
-                        assert_eq!(message.tag, i as u32);
+                        assert_eq!(message.tag, i as u32 + 1);
                         assert_eq!(message.name, names[i]);
                         if let Some(label) = labels[i] {
                             assert_eq!(message.label, Label::from_str(label));
                         } else {
                             assert_eq!(message.label, None);
                         }
-                        assert_eq!(message.ty, message_types[i]);
+                        assert_eq!(message.ty.name, message_types[i]);
                     },
                     Field::Map(map) => {
-                        assert_eq!(map.tag, i as u32);
+                        assert_eq!(map.tag, i as u32 + 1);
                         assert_eq!(map.name, names[i]);
-                        assert_eq!(map.key_ty, scalar::Ty::from_str(&scalar_types[i]).unwrap());
+                        assert_eq!(map.key_ty, scalar::Ty::from_str(&map_key_type(&scalar_types[i])).unwrap());
                     },
+                    Field::Oneof(..) => unreachable!(),
                 }
             }
         }
     }
+
+    #[test]
+    fn test_oneof_tags_are_unique() {
+        let input = quote!(
+            string name = 1;
+            oneof payload {
+                string text = 2;
+                bytes blob = 3;
+            }
+        );
+        let fields = syn::parse2::<Fields>(input).unwrap();
+        assert_eq!(fields.0.len(), 2);
+
+        let input = quote!(
+            string name = 1;
+            oneof payload {
+                string text = 2;
+                bytes blob = 1;
+            }
+        );
+        assert!(syn::parse2::<Fields>(input).is_err());
+
+        let input = quote!(
+            oneof payload {
+                string text = 2;
+                bytes blob = 2;
+            }
+        );
+        assert!(syn::parse2::<Fields>(input).is_err());
+
+        let input = quote!(
+            oneof payload {
+                string text = 2;
+            }
+            int32 count = 2;
+        );
+        assert!(syn::parse2::<Fields>(input).is_err());
+    }
+
+    #[test]
+    fn test_invalid_tags() {
+        let cases = [
+            (quote!(int32 a = 0;), "field number must be at least 1"),
+            (
+                quote!(string a = 536870912;),
+                "field number 536870912 is greater than the maximum of 536870911",
+            ),
+            (
+                quote!(Foo a = 4294967296;),
+                "field number 4294967296 is greater than the maximum of 536870911",
+            ),
+            (
+                quote!(map<string, int32> a = 19000;),
+                "field number 19000 is reserved for the protobuf implementation",
+            ),
+            (
+                quote!(oneof a { bool b = 19999; }),
+                "field number 19999 is reserved for the protobuf implementation",
+            ),
+        ];
+        for (input, expected) in cases {
+            let err = syn::parse2::<Fields>(input).err().unwrap();
+            assert_eq!(err.to_string(), expected);
+        }
+
+        let input = quote!(int32 a = 536870911; int32 b = 18999; int32 c = 20000;);
+        assert_eq!(syn::parse2::<Fields>(input).unwrap().0.len(), 3);
+    }
+
+    #[test]
+    fn test_reserved() {
+        let input = quote!(
+            reserved 2, 15, 9 to 11;
+            reserved "foo", "bar";
+            reserved 100 to max;
+            int32 a = 1;
+            int32 b = 12;
+        );
+        let fields = syn::parse2::<Fields>(input).unwrap();
+        assert_eq!(fields.0.len(), 2);
+        assert_eq!(
+            fields.reserved().ranges,
+            [2..=2, 15..=15, 9..=11, 100..=MAX_TAG]
+        );
+        assert_eq!(fields.reserved().names, ["foo", "bar"]);
+    }
+
+    #[test]
+    fn test_reserved_conflicts() {
+        let cases = [
+            (
+                quote!(reserved 9 to 11; int32 a = 10;),
+                "field number 10 is reserved but used by `a`",
+            ),
+            (
+                quote!(string a = 3; reserved 1, 3;),
+                "field number 3 is reserved but used by `a`",
+            ),
+            (
+                quote!(reserved "foo"; bool foo = 1;),
+                "field name `foo` is reserved",
+            ),
+            (
+                quote!(Foo bar = 1; reserved "baz", "bar";),
+                "field name `bar` is reserved",
+            ),
+            (
+                quote!(reserved 5; oneof a { bool b = 4; string c = 5; }),
+                "field number 5 is reserved but used by `c`",
+            ),
+            (
+                quote!(reserved 0;),
+                "reserved field number must be between 1 and 536870911",
+            ),
+            (
+                quote!(reserved 5 to 3;),
+                "reserved range end must not be less than its start",
+            ),
+            (
+                quote!(reserved "not valid";),
+                "reserved name must be a valid identifier",
+            ),
+            (quote!(reserved 1, "a";), "expected integer literal"),
+        ];
+        for (input, expected) in cases {
+            let err = syn::parse2::<Fields>(input).err().unwrap();
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_field_options() {
+        let input = quote!(
+            int32 a = 1 [deprecated = true];
+            Foo b = 2 [json_name = "bee"];
+            map<string, int32> c = 3 [deprecated = true, json_name = "sea"];
+            oneof d {
+                string e = 4 [deprecated = true];
+            }
+        );
+        let fields = syn::parse2::<Fields>(input).unwrap();
+        let options = fields.0.iter().map(Field::options).collect::<Vec<_>>();
+        assert!(options[0].unwrap().deprecated);
+        assert_eq!(options[1].unwrap().json_name("b"), "bee");
+        assert!(options[2].unwrap().deprecated);
+        assert_eq!(options[2].unwrap().json_name("c"), "sea");
+        assert!(options[3].is_none());
+        let Field::Oneof(oneof) = &fields.0[3] else {
+            unreachable!()
+        };
+        assert!(oneof.fields[0].options().unwrap().deprecated);
+
+        for input in [
+            quote!(Foo a = 1 [packed = true];),
+            quote!(map<string, int32> a = 1 [packed = false];),
+        ] {
+            let err = syn::parse2::<Fields>(input).err().unwrap();
+            assert_eq!(
+                err.to_string(),
+                "[packed] can only be specified for repeated numeric fields"
+            );
+        }
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let cases = [
+            (
+                quote!(unit32 a = 1;),
+                "unknown type `unit32`, did you mean `uint32`?",
+            ),
+            (
+                quote!(repeated strin a = 1;),
+                "unknown type `strin`, did you mean `string`?",
+            ),
+            (
+                quote!(repated int32 a = 1;),
+                "unknown label `repated`, did you mean `repeated`?",
+            ),
+            (
+                quote!(many int32 a = 1;),
+                "unknown label `many`, expected `optional` or `repeated`",
+            ),
+        ];
+        for (input, expected) in cases {
+            let err = syn::parse2::<Fields>(input).err().unwrap();
+            assert_eq!(err.to_string(), expected);
+        }
+
+        // Lowercase names that are not close to a scalar type are messages
+        let fields = syn::parse2::<Fields>(quote!(timestamp a = 1;)).unwrap();
+        assert!(matches!(&fields.0[0], Field::Message(field) if field.ty.name == "timestamp"));
+    }
+
+    #[test]
+    fn test_errors_are_aggregated() {
+        let input = quote!(
+            int32 a = 0;
+            unit32 b = 2;
+            string c = 3;
+            oneof d {
+                bool e = 4 [packed = true];
+            }
+            repated int32 f = 5;
+            bool g = 6;
+        );
+        let errors = syn::parse2::<Fields>(input)
+            .err()
+            .unwrap()
+            .into_iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "field number must be at least 1",
+                "unknown type `unit32`, did you mean `uint32`?",
+                "[packed] can only be specified for repeated numeric fields",
+                "unknown label `repated`, did you mean `repeated`?",
+            ]
+        );
+    }
 }
@@ -0,0 +1,116 @@
+use proc_macro2::Span;
+use syn::{
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+};
+
+use super::{
+    utils::{is_protobuf_reserve_key_word, parse_statements},
+    Field,
+};
+
+/// A oneof protobuf field: a set of fields of which at most one is set.
+#[allow(unused)]
+#[derive(Clone)]
+pub struct OneofField {
+    pub name: String,
+    /// The span of the name, to report clashes with the generated enum.
+    pub span: Span,
+    /// The member fields, each either `Field::Scalar` or `Field::Message`.
+    pub fields: Vec<Field>,
+}
+
+impl Parse for OneofField {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let oneof_kw = input.parse::<syn::Ident>()?;
+        if oneof_kw != "oneof" {
+            return Err(syn::Error::new(oneof_kw.span(), "expected oneof keyword"));
+        }
+
+        let name = input.call(syn::Ident::parse_any)?;
+        if is_protobuf_reserve_key_word(&name.to_string()) {
+            return Err(syn::Error::new(name.span(), "reserved keyword"));
+        }
+
+        let content;
+        syn::braced!(content in input);
+        let mut fields = Vec::new();
+        parse_statements(&content, |content| {
+            let span = content.span();
+            let field = content.parse::<Field>()?;
+            let has_label = match &field {
+                Field::Scalar(field) => field.label.is_some(),
+                Field::Message(field) => field.label.is_some(),
+                Field::Map(..) | Field::Oneof(..) => {
+                    return Err(syn::Error::new(
+                        span,
+                        "oneof members must be scalar or message fields",
+                    ))
+                }
+            };
+            if has_label {
+                return Err(syn::Error::new(span, "oneof members cannot have a label"));
+            }
+            fields.push(field);
+            Ok(())
+        })?;
+
+        if fields.is_empty() {
+            return Err(syn::Error::new(
+                name.span(),
+                "oneof must have at least one field",
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            span: name.span(),
+            fields,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::quote;
+
+    #[test]
+    fn test_parse_oneof() {
+        let input = quote!(
+            oneof payload {
+                string text = 3;
+                bytes blob = 4;
+                Attachment attachment = 5;
+            }
+        );
+        let oneof = syn::parse2::<OneofField>(input).unwrap();
+        assert_eq!(oneof.name, "payload");
+        assert_eq!(
+            oneof.fields.iter().map(Field::name).collect::<Vec<_>>(),
+            ["text", "blob", "attachment"]
+        );
+        assert_eq!(
+            oneof
+                .fields
+                .iter()
+                .flat_map(Field::tags)
+                .collect::<Vec<_>>(),
+            [3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn test_invalid_oneof() {
+        for input in [
+            quote!(oneof payload {}),
+            quote!(oneof payload { repeated string text = 1; }),
+            quote!(oneof payload { optional Attachment attachment = 1; }),
+            quote!(oneof payload { map<string, string> values = 1; }),
+            quote!(oneof payload { oneof inner { string text = 1; } }),
+            quote!(oneof message { string text = 1; }),
+        ] {
+            assert!(syn::parse2::<OneofField>(input).is_err());
+        }
+    }
+}
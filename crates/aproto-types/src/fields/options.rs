@@ -0,0 +1,320 @@
+use proc_macro2::Span;
+use syn::parse::{Parse, ParseStream};
+
+use super::{
+    scalar::Ty,
+    utils::{parse_int, peek_keyword},
+    Label,
+};
+
+/// The options given in brackets after a field's number, such as
+/// `[deprecated = true, packed = false]`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FieldOptions {
+    /// Set by `packed`, otherwise the proto3 default applies.
+    pub packed: Option<bool>,
+    /// Marks the generated Rust field `#[deprecated]`.
+    pub deprecated: bool,
+    /// The name of the field in the protobuf JSON mapping, instead of the
+    /// `lowerCamelCase` form of its name. aproto has no JSON support of its
+    /// own, so this is metadata only: generated messages list it in their
+    /// `JSON_NAMES` for code that maps them to JSON.
+    pub json_name: Option<String>,
+    /// The proto2 value of the field when it is not set.
+    pub default: Option<DefaultValue>,
+}
+
+/// A value given with `[default = ...]`. Its type is only checked against the
+/// field's once that is known, which for enum fields is after resolution.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DefaultValue {
+    Bool(bool),
+    /// An integer, which may also be the default of a `float` or `double`.
+    Int(i128),
+    /// The bits of an `f64`, so that `inf` and `nan` can be held.
+    Float(u64),
+    /// The default of a `string` field, or of a `bytes` field until its type
+    /// is known.
+    String(String),
+    /// The default of a `bytes` field, which need not be valid UTF-8.
+    Bytes(Vec<u8>),
+    /// The name of an enum value.
+    Ident(String),
+}
+
+impl Parse for DefaultValue {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let negative = input.peek(syn::Token![-]);
+        if negative {
+            input.parse::<syn::Token![-]>()?;
+        }
+        let sign = if negative { -1.0 } else { 1.0 };
+
+        let lookahead = input.lookahead1();
+        let value = if lookahead.peek(syn::LitInt) {
+            let value = parse_int::<i128>(&input.parse::<syn::LitInt>()?)?;
+            Self::Int(if negative { -value } else { value })
+        } else if lookahead.peek(syn::LitFloat) {
+            let value = input.parse::<syn::LitFloat>()?.base10_parse::<f64>()?;
+            Self::Float((sign * value).to_bits())
+        } else if peek_keyword(input, "inf") || peek_keyword(input, "nan") {
+            let ident = input.parse::<syn::Ident>()?;
+            let value = if ident == "inf" {
+                f64::INFINITY
+            } else {
+                f64::NAN
+            };
+            Self::Float((sign * value).to_bits())
+        } else if negative {
+            return Err(lookahead.error());
+        } else if lookahead.peek(syn::LitBool) {
+            Self::Bool(input.parse::<syn::LitBool>()?.value)
+        } else if lookahead.peek(syn::LitStr) {
+            Self::String(input.parse::<syn::LitStr>()?.value())
+        } else if lookahead.peek(syn::LitByteStr) {
+            Self::Bytes(input.parse::<syn::LitByteStr>()?.value())
+        } else if lookahead.peek(syn::Ident) {
+            Self::Ident(input.parse::<syn::Ident>()?.to_string())
+        } else {
+            return Err(lookahead.error());
+        };
+        Ok(value)
+    }
+}
+
+impl FieldOptions {
+    /// The name of the field in the JSON mapping.
+    pub fn json_name(&self, field_name: &str) -> String {
+        self.json_name
+            .clone()
+            .unwrap_or_else(|| default_json_name(field_name))
+    }
+
+    /// Checks that a `default` value suits a scalar field of the given type.
+    pub fn check_default(&self, span: Span, label: Option<&Label>, ty: &Ty) -> syn::Result<()> {
+        let Some(default) = &self.default else {
+            return Ok(());
+        };
+        if label == Some(&Label::Repeated) {
+            return Err(syn::Error::new(
+                span,
+                "repeated fields cannot have default values",
+            ));
+        }
+        let valid = match (ty, default) {
+            (Ty::Bool, DefaultValue::Bool(..)) => true,
+            (Ty::String | Ty::Bytes(..), DefaultValue::String(..)) => true,
+            (Ty::Bytes(..), DefaultValue::Bytes(..)) => true,
+            (Ty::Float | Ty::Double, DefaultValue::Int(..) | DefaultValue::Float(..)) => true,
+            (ty, DefaultValue::Int(value)) => {
+                ty.int_range().is_some_and(|range| range.contains(value))
+            }
+            _ => false,
+        };
+        if !valid {
+            return Err(syn::Error::new(
+                span,
+                format!("invalid default value for a `{ty}` field"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Rejects `packed` on a field that is not a repeated numeric field.
+    pub fn check_packable(&self, span: Span, packable: bool) -> syn::Result<()> {
+        if self.packed.is_some() && !packable {
+            return Err(syn::Error::new(
+                span,
+                "[packed] can only be specified for repeated numeric fields",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Parse for FieldOptions {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut options = Self::default();
+        if !input.peek(syn::token::Bracket) {
+            return Ok(options);
+        }
+
+        let content;
+        syn::bracketed!(content in input);
+        let mut seen = Vec::new();
+        while !content.is_empty() {
+            let option = content.parse::<syn::Ident>()?;
+            if seen.contains(&option) {
+                return Err(syn::Error::new(
+                    option.span(),
+                    format!("duplicate field option `{option}`"),
+                ));
+            }
+            content.parse::<syn::Token![=]>()?;
+            match option.to_string().as_str() {
+                "packed" => options.packed = Some(content.parse::<syn::LitBool>()?.value),
+                "deprecated" => options.deprecated = content.parse::<syn::LitBool>()?.value,
+                "json_name" => options.json_name = Some(content.parse::<syn::LitStr>()?.value()),
+                "default" => options.default = Some(content.parse()?),
+                _ => {
+                    return Err(syn::Error::new(
+                        option.span(),
+                        format!("unsupported field option `{option}`"),
+                    ))
+                }
+            }
+            seen.push(option);
+
+            if !content.is_empty() {
+                content.parse::<syn::Token![,]>()?;
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// The default JSON name of a field: its name in `lowerCamelCase`.
+pub fn default_json_name(name: &str) -> String {
+    let mut json_name = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            json_name.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            json_name.push(c);
+        }
+    }
+    json_name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scalar::BytesTy;
+    use quote::quote;
+
+    #[test]
+    fn test_parse_options() {
+        let options = syn::parse2::<FieldOptions>(quote!()).unwrap();
+        assert_eq!(options, FieldOptions::default());
+
+        let options = syn::parse2::<FieldOptions>(quote!([
+            deprecated = true,
+            packed = false,
+            json_name = "id"
+        ]))
+        .unwrap();
+        assert_eq!(
+            options,
+            FieldOptions {
+                packed: Some(false),
+                deprecated: true,
+                json_name: Some("id".to_string()),
+                default: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_default() {
+        let cases = [
+            (quote!(true), DefaultValue::Bool(true)),
+            (quote!(-12), DefaultValue::Int(-12)),
+            (quote!(017), DefaultValue::Int(15)),
+            (quote!(0x1F), DefaultValue::Int(31)),
+            (
+                quote!(18446744073709551615),
+                DefaultValue::Int(u64::MAX.into()),
+            ),
+            (quote!(1.5), DefaultValue::Float(1.5f64.to_bits())),
+            (
+                quote!(-inf),
+                DefaultValue::Float(f64::NEG_INFINITY.to_bits()),
+            ),
+            (quote!("a\tb"), DefaultValue::String("a\tb".to_string())),
+            (quote!(b"\xff"), DefaultValue::Bytes(vec![0xff])),
+            (quote!(ACTIVE), DefaultValue::Ident("ACTIVE".to_string())),
+        ];
+        for (value, expected) in cases {
+            let options = syn::parse2::<FieldOptions>(quote!([default = #value])).unwrap();
+            assert_eq!(options.default, Some(expected));
+        }
+        assert!(syn::parse2::<FieldOptions>(quote!([default = -ACTIVE])).is_err());
+    }
+
+    #[test]
+    fn test_check_default() {
+        let check = |value, label, ty| {
+            let options = syn::parse2::<FieldOptions>(quote!([default = #value])).unwrap();
+            options
+                .check_default(Span::call_site(), label, &ty)
+                .map_err(|error| error.to_string())
+        };
+        assert!(check(quote!(-1), None, Ty::Int32).is_ok());
+        assert!(check(quote!(2), Some(&Label::Optional), Ty::Double).is_ok());
+        assert!(check(quote!(nan), None, Ty::Float).is_ok());
+        assert!(check(quote!("x"), None, Ty::Bytes(BytesTy::Vec)).is_ok());
+        assert!(check(quote!(b"\xff"), None, Ty::Bytes(BytesTy::Vec)).is_ok());
+        assert_eq!(
+            check(quote!(b"x"), None, Ty::String),
+            Err("invalid default value for a `string` field".to_string())
+        );
+        assert_eq!(
+            check(quote!(-1), None, Ty::Uint32),
+            Err("invalid default value for a `uint32` field".to_string())
+        );
+        assert_eq!(
+            check(quote!(2147483648), None, Ty::Int32),
+            Err("invalid default value for a `int32` field".to_string())
+        );
+        assert_eq!(
+            check(quote!(1.5), None, Ty::Int64),
+            Err("invalid default value for a `int64` field".to_string())
+        );
+        assert_eq!(
+            check(quote!(true), Some(&Label::Repeated), Ty::Bool),
+            Err("repeated fields cannot have default values".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let cases = [
+            (quote!([lazy = true]), "unsupported field option `lazy`"),
+            (
+                quote!([deprecated = true, deprecated = false]),
+                "duplicate field option `deprecated`",
+            ),
+            (quote!([packed = 1]), "expected boolean literal"),
+            (quote!([json_name = id]), "expected string literal"),
+            (quote!([packed = true deprecated = true]), "expected `,`"),
+        ];
+        for (input, expected) in cases {
+            let err = syn::parse2::<FieldOptions>(input).err().unwrap();
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_json_name() {
+        let options = FieldOptions::default();
+        assert_eq!(options.json_name("user_id"), "userId");
+
+        let options = FieldOptions {
+            json_name: Some("uid".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(options.json_name("user_id"), "uid");
+    }
+
+    #[test]
+    fn test_default_json_name() {
+        assert_eq!(default_json_name("name"), "name");
+        assert_eq!(default_json_name("user_id"), "userId");
+        assert_eq!(default_json_name("http_2_port"), "http2Port");
+        assert_eq!(default_json_name("alreadyCamel"), "alreadyCamel");
+    }
+}
@@ -0,0 +1,141 @@
+use std::{fmt::Display, ops::RangeInclusive};
+
+use syn::parse::ParseStream;
+
+use super::{
+    utils::{parse_int, peek_keyword},
+    MAX_TAG, MIN_TAG,
+};
+
+/// A number that `reserved` statements reserve: a message's field numbers,
+/// or the values of an enum.
+pub trait ReservedNumber: Copy + Ord + Display + TryFrom<i128> {
+    const MIN: Self;
+    const MAX: Self;
+    /// What the numbers are called in error messages.
+    const KIND: &'static str;
+}
+
+impl ReservedNumber for u32 {
+    const MIN: Self = MIN_TAG;
+    const MAX: Self = MAX_TAG;
+    const KIND: &'static str = "field number";
+}
+
+impl ReservedNumber for i32 {
+    const MIN: Self = i32::MIN;
+    const MAX: Self = i32::MAX;
+    const KIND: &'static str = "enum value";
+}
+
+/// The numbers and names a message or an enum reserves with `reserved`
+/// statements, so that fields or values removed from it are never reused.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reserved<N = u32> {
+    pub ranges: Vec<RangeInclusive<N>>,
+    pub names: Vec<String>,
+}
+
+impl<N: ReservedNumber> Reserved<N> {
+    pub fn contains_number(&self, number: N) -> bool {
+        self.ranges.iter().any(|range| range.contains(&number))
+    }
+
+    pub fn contains_name(&self, name: &str) -> bool {
+        self.names.iter().any(|reserved| reserved == name)
+    }
+
+    /// Parses a `reserved 2, 15, 9 to 11;` or `reserved "foo", "bar";`
+    /// statement. Each statement reserves either numbers or names, and every
+    /// new number or name is checked by `check`, which returns an error
+    /// message if it is already used by a field or value.
+    pub fn parse_statement(
+        &mut self,
+        input: ParseStream,
+        check: impl Fn(&Self) -> Option<String>,
+    ) -> syn::Result<()>
+    where
+        Self: Default,
+    {
+        let keyword = input.parse::<syn::Ident>()?;
+        if keyword != "reserved" {
+            return Err(syn::Error::new(keyword.span(), "expected reserved keyword"));
+        }
+
+        let names = input.peek(syn::LitStr);
+        loop {
+            let span = input.span();
+            let mut reserved = Self::default();
+            if names {
+                reserved.names.push(parse_name(input)?);
+            } else {
+                reserved.ranges.push(parse_range(input)?);
+            }
+            if let Some(message) = check(&reserved) {
+                return Err(syn::Error::new(span, message));
+            }
+            self.ranges.extend(reserved.ranges);
+            self.names.extend(reserved.names);
+
+            if input.peek(syn::Token![;]) {
+                input.parse::<syn::Token![;]>()?;
+                return Ok(());
+            }
+            input.parse::<syn::Token![,]>()?;
+        }
+    }
+}
+
+fn parse_name(input: ParseStream) -> syn::Result<String> {
+    let lit = input.parse::<syn::LitStr>()?;
+    let name = lit.value();
+    if syn::parse_str::<syn::Ident>(&name).is_err() {
+        return Err(syn::Error::new(
+            lit.span(),
+            "reserved name must be a valid identifier",
+        ));
+    }
+    Ok(name)
+}
+
+/// Parses a single number, or an inclusive range such as `9 to 11` or
+/// `100 to max`.
+fn parse_range<N: ReservedNumber>(input: ParseStream) -> syn::Result<RangeInclusive<N>> {
+    let start = parse_number(input)?;
+    if !peek_keyword(input, "to") {
+        return Ok(start..=start);
+    }
+    input.parse::<syn::Ident>()?;
+
+    let end = if peek_keyword(input, "max") {
+        input.parse::<syn::Ident>()?;
+        N::MAX
+    } else {
+        let span = input.span();
+        let end = parse_number(input)?;
+        if end < start {
+            return Err(syn::Error::new(
+                span,
+                "reserved range end must not be less than its start",
+            ));
+        }
+        end
+    };
+    Ok(start..=end)
+}
+
+fn parse_number<N: ReservedNumber>(input: ParseStream) -> syn::Result<N> {
+    let lit = input.parse::<syn::LitInt>()?;
+    match N::try_from(parse_int::<i128>(&lit)?) {
+        Ok(number) if (N::MIN..=N::MAX).contains(&number) => Ok(number),
+        _ => Err(syn::Error::new(
+            lit.span(),
+            format!(
+                "reserved {} must be between {} and {}",
+                N::KIND,
+                N::MIN,
+                N::MAX
+            ),
+        )),
+    }
+}
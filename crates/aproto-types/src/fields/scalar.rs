@@ -1,10 +1,18 @@
 use anyhow::{anyhow, Error};
 use proc_macro2::TokenStream;
 use quote::quote;
-use std::fmt;
-use syn::parse::{Parse, ParseStream};
-
-use super::{utils::{is_protobuf_reserve_key_word, parse_label}, Label};
+use std::{fmt, ops::RangeInclusive};
+use syn::{
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+};
+
+use super::{
+    options::{DefaultValue, FieldOptions},
+    utils::{is_protobuf_reserve_key_word, parse_label, parse_tag},
+    Label,
+};
+use crate::file::Syntax;
 
 /// A scalar protobuf field.
 #[allow(unused)]
@@ -14,6 +22,7 @@ pub struct ScalarField {
     pub label: Option<Label>,
     pub ty: Ty,
     pub tag: u32,
+    pub options: FieldOptions,
 }
 
 impl ScalarField {
@@ -21,6 +30,15 @@ impl ScalarField {
         let ty = Ty::from_str(input);
         ty.is_ok()
     }
+
+    /// Whether a repeated field is encoded packed. Proto3 packs repeated
+    /// numeric fields unless the field opts out with `[packed = false]`, while
+    /// proto2 only packs them when asked to with `[packed = true]`.
+    pub fn is_packed(&self, syntax: Syntax) -> bool {
+        self.label == Some(Label::Repeated)
+            && self.ty.is_packable()
+            && self.options.packed.unwrap_or(syntax == Syntax::Proto3)
+    }
 }
 
 impl Parse for ScalarField {
@@ -41,20 +59,29 @@ impl Parse for ScalarField {
             let ty = input.parse::<syn::Ident>()?;
             let ty = Ty::from_str(&ty.to_string())
                 .map_err(|e| syn::Error::new(input.span(), e.to_string()))?;
-            let name = input.parse::<syn::Ident>()?;
+            let name = input.call(syn::Ident::parse_any)?;
             if is_protobuf_reserve_key_word(&name.to_string()) {
                 return Err(syn::Error::new(input.span(), "reserved keyword"));
             }
             let _ = input.parse::<syn::Token![=]>()?;
-            let tag = input.parse::<syn::LitInt>()?;
-            let tag = tag.base10_parse::<u32>()?;
+            let tag = parse_tag(input)?;
+            let options_span = input.span();
+            let mut options = input.parse::<FieldOptions>()?;
             let _ = input.parse::<syn::Token![;]>()?;
 
+            let packable = label == Some(Label::Repeated) && ty.is_packable();
+            options.check_packable(options_span, packable)?;
+            options.check_default(options_span, label.as_ref(), &ty)?;
+            if let (Ty::Bytes(..), Some(DefaultValue::String(value))) = (&ty, &options.default) {
+                options.default = Some(DefaultValue::Bytes(value.clone().into_bytes()));
+            }
+
             return Ok(ScalarField {
                 name: name.to_string(),
                 label,
                 ty,
                 tag,
+                options,
             });
         }
         Err(syn::Error::new(input.span(), "not a scalar field"))
@@ -83,13 +110,25 @@ pub enum Ty {
     Int64,
     Uint32,
     Uint64,
+    Sint32,
+    Sint64,
+    Fixed32,
+    Fixed64,
+    Sfixed32,
+    Sfixed64,
     Bool,
     String,
     Bytes(BytesTy),
 }
 
-#[allow(unused)]
+#[allow(unused, clippy::should_implement_trait)]
 impl Ty {
+    /// The protobuf names of all scalar types.
+    pub const NAMES: [&'static str; 15] = [
+        "double", "float", "int32", "int64", "uint32", "uint64", "sint32", "sint64", "fixed32",
+        "fixed64", "sfixed32", "sfixed64", "bool", "string", "bytes",
+    ];
+
     /// Converts a protobuf type string into its corresponding `Ty` enum variant.
     ///
     /// This function is used to parse protobuf type definitions into their internal
@@ -105,9 +144,11 @@ impl Ty {
     /// # Supported Types
     /// - Floating point: "float" (32-bit), "double" (64-bit)
     /// - Integers: "int32", "int64", "uint32", "uint64"
+    /// - ZigZag-encoded integers: "sint32", "sint64"
+    /// - Fixed-width integers: "fixed32", "fixed64", "sfixed32", "sfixed64"
     /// - Boolean: "bool"
     /// - String: "string"
-    /// - Bytes: "bytes" (converts to &[u8])
+    /// - Bytes: "bytes" (converts to Vec<u8>)
     ///
     /// # Note
     /// The function trims whitespace from the input string before matching,
@@ -122,6 +163,12 @@ impl Ty {
             "int64" => Ty::Int64,
             "uint32" => Ty::Uint32,
             "uint64" => Ty::Uint64,
+            "sint32" => Ty::Sint32,
+            "sint64" => Ty::Sint64,
+            "fixed32" => Ty::Fixed32,
+            "fixed64" => Ty::Fixed64,
+            "sfixed32" => Ty::Sfixed32,
+            "sfixed64" => Ty::Sfixed64,
             "bool" => Ty::Bool,
             "string" => Ty::String,
             "bytes" => Ty::Bytes(BytesTy::Vec),
@@ -148,12 +195,43 @@ impl Ty {
             Ty::Int64 => quote!(i64),
             Ty::Uint32 => quote!(u32),
             Ty::Uint64 => quote!(u64),
+            Ty::Sint32 => quote!(i32),
+            Ty::Sint64 => quote!(i64),
+            Ty::Fixed32 => quote!(u32),
+            Ty::Fixed64 => quote!(u64),
+            Ty::Sfixed32 => quote!(i32),
+            Ty::Sfixed64 => quote!(i64),
             Ty::Bool => quote!(bool),
             Ty::String => quote!(String),
-            Ty::Bytes(..) => quote!(&[u8]),
+            Ty::Bytes(BytesTy::Vec) => quote!(Vec<u8>),
+            Ty::Bytes(BytesTy::Bytes) => quote!(::aproto::bytes::Bytes),
         }
     }
 
+    /// Returns true if repeated fields of this type can use the packed encoding,
+    /// which is every scalar type except `string` and `bytes`.
+    pub fn is_packable(&self) -> bool {
+        !matches!(self, Ty::String | Ty::Bytes(..))
+    }
+
+    /// The values an integer type can hold, or `None` for the other types.
+    pub fn int_range(&self) -> Option<RangeInclusive<i128>> {
+        let range = match self {
+            Ty::Int32 | Ty::Sint32 | Ty::Sfixed32 => i128::from(i32::MIN)..=i128::from(i32::MAX),
+            Ty::Int64 | Ty::Sint64 | Ty::Sfixed64 => i128::from(i64::MIN)..=i128::from(i64::MAX),
+            Ty::Uint32 | Ty::Fixed32 => 0..=i128::from(u32::MAX),
+            Ty::Uint64 | Ty::Fixed64 => 0..=i128::from(u64::MAX),
+            _ => return None,
+        };
+        Some(range)
+    }
+
+    /// Returns true if the type may be used as the key of a map field, which
+    /// excludes floating point types and `bytes`.
+    pub fn is_valid_map_key(&self) -> bool {
+        !matches!(self, Ty::Float | Ty::Double | Ty::Bytes(..))
+    }
+
     /// Returns the type as it appears in protobuf field declarations
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -163,6 +241,12 @@ impl Ty {
             Ty::Int64 => "int64",
             Ty::Uint32 => "uint32",
             Ty::Uint64 => "uint64",
+            Ty::Sint32 => "sint32",
+            Ty::Sint64 => "sint64",
+            Ty::Fixed32 => "fixed32",
+            Ty::Fixed64 => "fixed64",
+            Ty::Sfixed32 => "sfixed32",
+            Ty::Sfixed64 => "sfixed64",
             Ty::Bool => "bool",
             Ty::String => "string",
             Ty::Bytes(..) => "bytes",
@@ -199,7 +283,8 @@ impl Parse for Ty {
     }
 }
 
-#[allow(unused)]
+/// How a `bytes` field is stored. Fields parse as `Vec`; the macros switch them
+/// to `Bytes` with their `#![aproto(bytes = "bytes")]` setting.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum BytesTy {
     Vec,
@@ -208,7 +293,7 @@ pub enum BytesTy {
 
 #[cfg(test)]
 mod tests {
-    use crate::fields::utils::{is_rust_reserve_key_word, is_protobuf_reserve_key_word};
+    use crate::fields::utils::is_protobuf_reserve_key_word;
 
     use super::*;
     use proptest::prelude::*;
@@ -216,7 +301,9 @@ mod tests {
     proptest! {
         #[test]
         fn test_all_scalar_fields_with_optional(
-            name in "[a-z][a-z0-9_]*",
+            name in "[a-z][a-z0-9_]*".prop_filter("filtered out reserved words", |name| {
+                !is_protobuf_reserve_key_word(name)
+            }),
             ty in prop_oneof!(
                 Just("uint32"),
                 Just("int32"),
@@ -225,6 +312,10 @@ mod tests {
                 Just("double"),
                 Just("float"),
                 Just("uint64"),
+                Just("sint32"),
+                Just("sint64"),
+                Just("fixed32"),
+                Just("sfixed64"),
                 Just("bytes"),
             ),
             tag in 1..=100u32,
@@ -233,9 +324,9 @@ mod tests {
                 Just("repeated"),
             ),
         ) {
-            let name_ident = syn::parse_str::<syn::Ident>(&name).unwrap();
-            let ty_ident = syn::parse_str::<syn::Ident>(&ty).unwrap();
-            let label_ident = syn::parse_str::<syn::Ident>(&label).unwrap();
+            let name_ident = syn::Ident::new(&name, proc_macro2::Span::call_site());
+            let ty_ident = syn::parse_str::<syn::Ident>(ty).unwrap();
+            let label_ident = syn::parse_str::<syn::Ident>(label).unwrap();
 
             let input = quote!(#label_ident #ty_ident #name_ident = #tag;);
             let field = syn::parse2::<ScalarField>(input).unwrap();
@@ -248,12 +339,59 @@ mod tests {
 
             assert_eq!(field.name, name);
             assert_eq!(field.tag, tag);
-            assert_eq!(field.ty, Ty::from_str(&ty).unwrap());
+            assert_eq!(field.ty, Ty::from_str(ty).unwrap());
             assert_eq!(field.label, expected_label);
+            assert_eq!(field.options.packed, None);
         }
 
     }
 
+    #[test]
+    fn test_packed_option() {
+        let field = syn::parse2::<ScalarField>(quote!(repeated int32 ids = 1;)).unwrap();
+        assert!(field.is_packed(Syntax::Proto3));
+
+        let field =
+            syn::parse2::<ScalarField>(quote!(repeated int32 ids = 1 [packed = false];)).unwrap();
+        assert_eq!(field.options.packed, Some(false));
+        assert!(!field.is_packed(Syntax::Proto3));
+
+        let field =
+            syn::parse2::<ScalarField>(quote!(repeated double ids = 1 [packed = true];)).unwrap();
+        assert!(field.is_packed(Syntax::Proto3));
+
+        let field = syn::parse2::<ScalarField>(quote!(repeated string names = 1;)).unwrap();
+        assert!(!field.is_packed(Syntax::Proto3));
+
+        let field = syn::parse2::<ScalarField>(quote!(int32 id = 1;)).unwrap();
+        assert!(!field.is_packed(Syntax::Proto3));
+
+        let field = syn::parse2::<ScalarField>(quote!(repeated int32 ids = 1;)).unwrap();
+        assert!(!field.is_packed(Syntax::Proto2));
+
+        let field =
+            syn::parse2::<ScalarField>(quote!(repeated int32 ids = 1 [packed = true];)).unwrap();
+        assert!(field.is_packed(Syntax::Proto2));
+    }
+
+    #[test]
+    fn test_invalid_packed_option() {
+        assert!(
+            syn::parse2::<ScalarField>(quote!(repeated string names = 1 [packed = true];)).is_err()
+        );
+        assert!(syn::parse2::<ScalarField>(quote!(int32 id = 1 [packed = true];)).is_err());
+        assert!(syn::parse2::<ScalarField>(quote!(repeated int32 ids = 1 [packed = 1];)).is_err());
+        assert!(syn::parse2::<ScalarField>(quote!(repeated int32 ids = 1 [lazy = true];)).is_err());
+    }
+
+    #[test]
+    fn test_rust_keyword_names() {
+        for name in ["type", "match", "self", "async"] {
+            let field = syn::parse_str::<ScalarField>(&format!("string {name} = 1;")).unwrap();
+            assert_eq!(field.name, name);
+        }
+    }
+
     proptest! {
         #[test]
         fn test_multiple_scalar_fields(
@@ -261,8 +399,7 @@ mod tests {
                 // Reuse the same name validation strategy
                 let is_valid_name = |name: &str| {
                     !is_protobuf_reserve_key_word(name) &&
-                    !is_rust_reserve_key_word(name) &&
-                    name.chars().next().map_or(false, |c| c.is_ascii_alphabetic()) &&
+                    name.chars().next().is_some_and(|c| c.is_ascii_alphabetic()) &&
                     name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
                 };
 
@@ -280,6 +417,10 @@ mod tests {
                         Just("double"),
                         Just("float"),
                         Just("uint64"),
+                        Just("sint32"),
+                        Just("sint64"),
+                        Just("fixed64"),
+                        Just("sfixed32"),
                         Just("bytes"),
                     ], 100..=100),
                     prop::collection::vec(1..500u32, 100..=100),
@@ -295,7 +436,7 @@ mod tests {
             let labels = &labels[..num_fields];
             let tys = &ty[..num_fields];
 
-            let name_idents: Vec<_> = names.iter().map(|n| syn::parse_str::<syn::Ident>(n).unwrap()).collect();
+            let name_idents: Vec<_> = names.iter().map(|n| syn::Ident::new(n, proc_macro2::Span::call_site())).collect();
             let ty_idents: Vec<_> = tys.iter().map(|t| syn::parse_str::<syn::Ident>(t).unwrap()).collect();
             let label_idents: Vec<_> = labels.iter().map(|l| syn::parse_str::<syn::Ident>(l).unwrap()).collect();
 
@@ -0,0 +1,89 @@
+use proc_macro2::Span;
+use syn::parse::{Parse, ParseStream};
+
+/// A reference to a message or enum by name, as written in a field. The name
+/// may be dotted, such as `Outer.Inner`, and is fully qualified when it starts
+/// with a dot, such as `.foo.bar.Outer.Inner`.
+#[derive(Clone, Debug)]
+pub struct TypeRef {
+    pub name: String,
+    /// Where the reference is written, used to report unknown types.
+    pub span: Span,
+}
+
+/// References are equal when they name the same type, wherever they appear.
+impl PartialEq for TypeRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for TypeRef {}
+
+impl TypeRef {
+    /// Whether the name starts with a dot, so that it is resolved from the
+    /// root rather than from the scope it is written in.
+    pub fn is_fully_qualified(&self) -> bool {
+        self.name.starts_with('.')
+    }
+
+    /// The dot-separated parts of the name, without the leading dot of a
+    /// fully qualified name.
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.name.trim_start_matches('.').split('.')
+    }
+}
+
+impl Parse for TypeRef {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let span = input.span();
+        let mut name = String::new();
+        if input.peek(syn::Token![.]) {
+            input.parse::<syn::Token![.]>()?;
+            name.push('.');
+        }
+        name.push_str(&input.parse::<syn::Ident>()?.to_string());
+        while input.peek(syn::Token![.]) {
+            input.parse::<syn::Token![.]>()?;
+            name.push('.');
+            name.push_str(&input.parse::<syn::Ident>()?.to_string());
+        }
+        Ok(Self { name, span })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::quote;
+
+    #[test]
+    fn test_parse_type_ref() {
+        let cases = [
+            (quote!(Person), "Person", false),
+            (quote!(Outer.Inner), "Outer.Inner", false),
+            (quote!(foo.bar.Person), "foo.bar.Person", false),
+            (quote!(.foo.bar.Outer.Inner), ".foo.bar.Outer.Inner", true),
+        ];
+        for (input, name, fully_qualified) in cases {
+            let ty = syn::parse2::<TypeRef>(input).unwrap();
+            assert_eq!(ty.name, name);
+            assert_eq!(ty.is_fully_qualified(), fully_qualified);
+        }
+
+        let ty = syn::parse2::<TypeRef>(quote!(.foo.Person)).unwrap();
+        assert_eq!(ty.segments().collect::<Vec<_>>(), ["foo", "Person"]);
+    }
+
+    #[test]
+    fn test_invalid_type_ref() {
+        for input in [
+            quote!(Outer.),
+            quote!(..Person),
+            quote!(Outer..Inner),
+            quote!(.),
+        ] {
+            assert!(syn::parse2::<TypeRef>(input).is_err());
+        }
+    }
+}
@@ -1,8 +1,12 @@
-use syn::parse::ParseStream;
+use proc_macro2::TokenTree;
+use syn::{
+    ext::IdentExt,
+    parse::{discouraged::Speculative, ParseStream},
+};
 
 use crate::fields::scalar::ScalarField;
 
-use super::Label;
+use super::{Label, MAX_TAG, MIN_TAG, RESERVED_TAGS};
 
 pub fn parse_label(input: ParseStream) -> syn::Result<Option<Label>> {
     let fork = input.fork();
@@ -17,6 +21,184 @@ pub fn parse_label(input: ParseStream) -> syn::Result<Option<Label>> {
     Ok(None)
 }
 
+/// Whether the next token is the given keyword, without consuming it.
+pub fn peek_keyword(input: ParseStream, keyword: &str) -> bool {
+    input
+        .fork()
+        .parse::<syn::Ident>()
+        .is_ok_and(|ident| ident == keyword)
+}
+
+/// Whether the next tokens start an `option` statement, rather than a value
+/// or field named `option`.
+pub fn peek_option(input: ParseStream) -> bool {
+    peek_keyword(input, "option") && !input.peek2(syn::Token![=])
+}
+
+/// Parses an `option name = value;` statement of a file, message or enum.
+/// Such options do not affect the generated code, so they are only checked
+/// to be well formed and then ignored. The name may refer to an extension in
+/// parentheses, such as `(my.option).field`, and the value may be a literal,
+/// an identifier or a braced aggregate.
+pub fn parse_option(input: ParseStream) -> syn::Result<()> {
+    input.parse::<syn::Ident>()?;
+    if input.peek(syn::token::Paren) {
+        let content;
+        syn::parenthesized!(content in input);
+        content.parse::<proc_macro2::TokenStream>()?;
+    } else {
+        input.call(syn::Ident::parse_any)?;
+    }
+    while input.peek(syn::Token![.]) {
+        input.parse::<syn::Token![.]>()?;
+        input.call(syn::Ident::parse_any)?;
+    }
+    input.parse::<syn::Token![=]>()?;
+    if input.peek(syn::Token![;]) {
+        return Err(input.error("expected an option value"));
+    }
+    while !input.is_empty() && !input.peek(syn::Token![;]) {
+        input.parse::<TokenTree>()?;
+    }
+    input.parse::<syn::Token![;]>()?;
+    Ok(())
+}
+
+/// The value of an integer literal as protobuf reads it: hexadecimal after
+/// `0x`, octal after any other leading `0`, and decimal otherwise.
+pub fn parse_int<N: TryFrom<i128>>(lit: &syn::LitInt) -> syn::Result<N> {
+    let repr = lit.to_string();
+    let (negative, digits) = match repr.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, repr.as_str()),
+    };
+    let value = if digits.len() > 1
+        && digits.starts_with('0')
+        && digits.bytes().all(|b| b.is_ascii_digit())
+    {
+        let value = i128::from_str_radix(&digits[1..], 8)
+            .map_err(|_| syn::Error::new(lit.span(), "invalid octal literal"))?;
+        if negative {
+            -value
+        } else {
+            value
+        }
+    } else {
+        lit.base10_parse::<i128>()?
+    };
+    N::try_from(value)
+        .map_err(|_| syn::Error::new(lit.span(), "number too large to fit in target type"))
+}
+
+/// Parses a field number, reporting numbers protobuf does not allow at the
+/// literal.
+pub fn parse_tag(input: ParseStream) -> syn::Result<u32> {
+    let lit = input.parse::<syn::LitInt>()?;
+    let tag = parse_int::<i128>(&lit)?;
+    if tag < i128::from(MIN_TAG) {
+        return Err(syn::Error::new(
+            lit.span(),
+            format!("field number must be at least {MIN_TAG}"),
+        ));
+    }
+    if tag > i128::from(MAX_TAG) {
+        return Err(syn::Error::new(
+            lit.span(),
+            format!("field number {tag} is greater than the maximum of {MAX_TAG}"),
+        ));
+    }
+    let tag = tag as u32;
+    if RESERVED_TAGS.contains(&tag) {
+        return Err(syn::Error::new(
+            lit.span(),
+            format!("field number {tag} is reserved for the protobuf implementation"),
+        ));
+    }
+    Ok(tag)
+}
+
+/// Parses statements until `input` is empty, reporting every error rather
+/// than only the first. A statement that fails to parse is skipped up to and
+/// including its `;` or braced block, and parsing continues after it.
+pub fn parse_statements(
+    input: ParseStream,
+    mut parse: impl FnMut(ParseStream) -> syn::Result<()>,
+) -> syn::Result<()> {
+    let mut errors = Vec::new();
+    while !input.is_empty() {
+        let fork = input.fork();
+        match parse(&fork) {
+            Ok(()) => input.advance_to(&fork),
+            Err(error) => {
+                errors.push(error);
+                skip_statement(input)?;
+            }
+        }
+    }
+    combine(errors)
+}
+
+fn skip_statement(input: ParseStream) -> syn::Result<()> {
+    while !input.is_empty() {
+        match input.parse::<TokenTree>()? {
+            TokenTree::Punct(punct) if punct.as_char() == ';' => break,
+            TokenTree::Group(group) if group.delimiter() == proc_macro2::Delimiter::Brace => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Combines errors into a single one reporting all of them.
+pub fn combine(errors: impl IntoIterator<Item = syn::Error>) -> syn::Result<()> {
+    match errors.into_iter().reduce(|mut error, next| {
+        error.combine(next);
+        error
+    }) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Finds the candidate closest to a misspelled name, if any is close enough
+/// to be a likely typo.
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The number of single character insertions, deletions, substitutions and
+/// swaps of adjacent characters that turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
 pub fn is_protobuf_reserve_key_word(word: &str) -> bool {
 
     if ScalarField::is_scalar_field(word) {
@@ -32,7 +214,7 @@ pub fn is_protobuf_reserve_key_word(word: &str) -> bool {
         "message" | "enum" | "service" | "rpc" | "extend" |
         "extensions" | "option" | "package" | "import" |
         "public" | "weak" | "oneof" | "map" | "reserved" |
-        "syntax" | "to" | "max" | "stream"
+        "syntax" | "to" | "max" | "stream" | "group"
     )
 }
 
@@ -50,3 +232,70 @@ pub fn is_rust_reserve_key_word(word: &str) -> bool {
         "try" | "union"
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_int() {
+        let parse = |lit: &str| parse_int::<i32>(&syn::parse_str(lit).unwrap());
+        assert_eq!(parse("17").unwrap(), 17);
+        assert_eq!(parse("017").unwrap(), 15);
+        assert_eq!(parse("-017").unwrap(), -15);
+        assert_eq!(parse("0x1F").unwrap(), 31);
+        assert_eq!(parse("0").unwrap(), 0);
+        assert_eq!(parse("-1").unwrap(), -1);
+        assert_eq!(
+            parse("018").unwrap_err().to_string(),
+            "invalid octal literal"
+        );
+        assert_eq!(
+            parse("2147483648").unwrap_err().to_string(),
+            "number too large to fit in target type"
+        );
+    }
+
+    #[test]
+    fn test_parse_option() {
+        let parse = |input| {
+            syn::parse::Parser::parse_str(parse_option, input).map_err(|error| error.to_string())
+        };
+        assert!(parse(r#"option go_package = "x/y";"#).is_ok());
+        assert!(parse("option optimize_for = SPEED;").is_ok());
+        assert!(parse("option (my.option).type = -1;").is_ok());
+        assert!(parse("option (my.option) = { a: 1 b: \"x\" };").is_ok());
+        assert_eq!(
+            parse("option java_package;"),
+            Err("expected `=`".to_string())
+        );
+        assert_eq!(
+            parse("option java_package = ;"),
+            Err("expected an option value".to_string())
+        );
+        assert_eq!(
+            parse("option deprecated = true"),
+            Err("expected `;`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("uint32", "uint32"), 0);
+        assert_eq!(edit_distance("unit32", "uint32"), 1);
+        assert_eq!(edit_distance("strin", "string"), 1);
+        assert_eq!(edit_distance("Adress", "Address"), 1);
+        assert_eq!(edit_distance("int32", "int64"), 2);
+        assert_eq!(edit_distance("", "bool"), 4);
+    }
+
+    #[test]
+    fn test_suggest() {
+        let candidates = ["int32", "uint32", "sint32", "string"];
+        assert_eq!(suggest("unit32", candidates), Some("uint32"));
+        assert_eq!(suggest("int23", candidates), Some("int32"));
+        assert_eq!(suggest("strng", candidates), Some("string"));
+        assert_eq!(suggest("Person", candidates), None);
+        assert_eq!(suggest("a", ["b"]), Some("b"));
+    }
+}
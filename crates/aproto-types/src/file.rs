@@ -0,0 +1,420 @@
+use proc_macro2::{Delimiter, TokenTree};
+use syn::parse::{Parse, ParseStream};
+
+use crate::{
+    enums::ProtobufEnumDescriptor,
+    fields::utils::{parse_option, parse_statements, peek_keyword, peek_option},
+    ProtobufDescriptor, ProtobufMessageDescriptor,
+};
+
+/// The protobuf language version a file is written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Syntax {
+    /// The older version, with required fields, default values and groups.
+    Proto2,
+    #[default]
+    Proto3,
+}
+
+/// A whole protobuf file: an optional `syntax` and `package` statement
+/// followed by any number of imports, messages and enums.
+#[allow(unused)]
+#[derive(Default)]
+pub struct ProtobufFileDescriptor {
+    pub syntax: Syntax,
+    /// The dotted package name, such as `foo.bar`.
+    pub package: Option<String>,
+    /// The paths of the files imported with `import "a/b.proto";`.
+    pub imports: Vec<syn::LitStr>,
+    /// The imported files, once loaded, whose types the file's fields may
+    /// refer to.
+    pub dependencies: Vec<ProtobufFileDescriptor>,
+    pub messages: Vec<ProtobufMessageDescriptor>,
+    pub enums: Vec<ProtobufEnumDescriptor>,
+}
+
+impl ProtobufFileDescriptor {
+    /// Whether a top-level message or enum with the given name is declared in
+    /// the file.
+    pub fn declares(&self, name: &str) -> bool {
+        self.messages.iter().any(|message| message.name == name)
+            || self.enums.iter().any(|e| e.name == name)
+    }
+}
+
+impl From<ProtobufDescriptor> for ProtobufFileDescriptor {
+    fn from(descriptor: ProtobufDescriptor) -> Self {
+        let mut file = Self::default();
+        match descriptor {
+            ProtobufDescriptor::Message(message) => file.messages.push(message),
+            ProtobufDescriptor::Enum(e) => file.enums.push(e),
+        }
+        file
+    }
+}
+
+impl Parse for ProtobufFileDescriptor {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut file = Self::default();
+
+        if peek_keyword(input, "syntax") {
+            file.syntax = parse_syntax(input)?;
+        }
+
+        parse_statements(input, |input| file.parse_statement(input))?;
+        Ok(file)
+    }
+}
+
+impl ProtobufFileDescriptor {
+    /// Adds a statement that may follow the `syntax` statement to the file.
+    /// Services and extensions are skipped, as they do not change the
+    /// generated messages.
+    pub fn parse_statement(&mut self, input: ParseStream) -> syn::Result<()> {
+        let span = input.span();
+        if peek_keyword(input, "package") {
+            if self.package.is_some() {
+                return Err(syn::Error::new(span, "duplicate package statement"));
+            }
+            self.package = Some(parse_package(input)?);
+            return Ok(());
+        }
+        if peek_keyword(input, "import") {
+            self.imports.push(parse_import(input)?);
+            return Ok(());
+        }
+        if peek_option(input) {
+            return parse_option(input);
+        }
+        if peek_keyword(input, "service") || peek_keyword(input, "extend") {
+            return skip_block(input);
+        }
+
+        match ProtobufDescriptor::parse_with_syntax(input, self.syntax)? {
+            ProtobufDescriptor::Message(message) if !self.declares(&message.name) => {
+                self.messages.push(message)
+            }
+            ProtobufDescriptor::Enum(e) if !self.declares(&e.name) => self.enums.push(e),
+            _ => return Err(syn::Error::new(span, "duplicate type name")),
+        }
+        Ok(())
+    }
+}
+
+/// Parses `syntax = "proto2";` or `syntax = "proto3";`.
+fn parse_syntax(input: ParseStream) -> syn::Result<Syntax> {
+    input.parse::<syn::Ident>()?;
+    input.parse::<syn::Token![=]>()?;
+    let syntax = input.parse::<syn::LitStr>()?;
+    input.parse::<syn::Token![;]>()?;
+    match syntax.value().as_str() {
+        "proto2" => Ok(Syntax::Proto2),
+        "proto3" => Ok(Syntax::Proto3),
+        _ => Err(syn::Error::new(
+            syntax.span(),
+            "unsupported syntax, expected \"proto2\" or \"proto3\"",
+        )),
+    }
+}
+
+/// Parses `package foo.bar;` into `foo.bar`.
+fn parse_package(input: ParseStream) -> syn::Result<String> {
+    input.parse::<syn::Ident>()?;
+    let mut package = input.parse::<syn::Ident>()?.to_string();
+    while input.peek(syn::Token![.]) {
+        input.parse::<syn::Token![.]>()?;
+        package.push('.');
+        package.push_str(&input.parse::<syn::Ident>()?.to_string());
+    }
+    input.parse::<syn::Token![;]>()?;
+    Ok(package)
+}
+
+/// Parses `import "a/b.proto";` into its path. `public` and `weak` imports
+/// are read like any other.
+fn parse_import(input: ParseStream) -> syn::Result<syn::LitStr> {
+    input.parse::<syn::Ident>()?;
+    if peek_keyword(input, "public") || peek_keyword(input, "weak") {
+        input.parse::<syn::Ident>()?;
+    }
+    let path = input.parse::<syn::LitStr>()?;
+    input.parse::<syn::Token![;]>()?;
+    Ok(path)
+}
+
+/// Skips a `service Foo { ... }` or `extend Foo { ... }` block.
+fn skip_block(input: ParseStream) -> syn::Result<()> {
+    let keyword = input.parse::<syn::Ident>()?;
+    while !input.is_empty() {
+        if let TokenTree::Group(group) = input.parse::<TokenTree>()? {
+            if group.delimiter() == Delimiter::Brace {
+                return Ok(());
+            }
+        }
+    }
+    Err(syn::Error::new(
+        keyword.span(),
+        format!("expected the body of the `{keyword}` block"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{options::DefaultValue, Field, Label};
+    use quote::quote;
+
+    #[test]
+    fn test_parse_file_descriptor() {
+        let input = quote!(
+            syntax = "proto3";
+            package foo.bar_v1;
+            import "common.proto";
+            import public "other/types.proto";
+
+            message Person {
+                string name = 1;
+                Status status = 2;
+            }
+
+            enum Status {
+                UNKNOWN = 0;
+            }
+
+            message Empty {}
+        );
+        let file = syn::parse2::<ProtobufFileDescriptor>(input).unwrap();
+        assert_eq!(file.syntax, Syntax::Proto3);
+        assert_eq!(file.package.as_deref(), Some("foo.bar_v1"));
+        let imports = file
+            .imports
+            .iter()
+            .map(syn::LitStr::value)
+            .collect::<Vec<_>>();
+        assert_eq!(imports, ["common.proto", "other/types.proto"]);
+        assert_eq!(file.messages.len(), 2);
+        assert_eq!(file.enums.len(), 1);
+        assert!(file.declares("Person"));
+        assert!(file.declares("Status"));
+    }
+
+    #[test]
+    fn test_options_are_ignored() {
+        let input = quote!(
+            syntax = "proto3";
+            option go_package = "x/y";
+            package foo;
+            option (my.file_option).enabled = true;
+
+            message Person {
+                option deprecated = true;
+                option (my.message_option) = { name: "person" };
+                string name = 1;
+            }
+
+            enum Status {
+                option allow_alias = true;
+                UNKNOWN = 0;
+                option = 1;
+            }
+        );
+        let file = syn::parse2::<ProtobufFileDescriptor>(input).unwrap();
+        assert_eq!(file.package.as_deref(), Some("foo"));
+        assert_eq!(file.messages[0].fields.0.len(), 1);
+        let values = file.enums[0]
+            .values
+            .iter()
+            .map(|value| value.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(values, ["UNKNOWN", "option"]);
+
+        let err = syn::parse2::<ProtobufFileDescriptor>(quote!(option go_package;))
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "expected `=`");
+    }
+
+    #[test]
+    fn test_services_and_extensions_are_skipped() {
+        let input = quote!(
+            syntax = "proto2";
+            import "google/protobuf/descriptor.proto";
+
+            service Search {
+                option deprecated = true;
+                rpc Find (Query) returns (stream Query) {
+                    option idempotency_level = NO_SIDE_EFFECTS;
+                }
+            }
+
+            extend google.protobuf.FieldOptions {
+                optional string label = 50000;
+            }
+
+            message Query {
+                optional string text = 1;
+            }
+        );
+        let file = syn::parse2::<ProtobufFileDescriptor>(input).unwrap();
+        assert_eq!(file.messages.len(), 1);
+        assert!(file.declares("Query"));
+
+        let err = syn::parse2::<ProtobufFileDescriptor>(quote!(service Search;))
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "expected the body of the `service` block");
+    }
+
+    #[test]
+    fn test_parse_file_without_header() {
+        let file = syn::parse2::<ProtobufFileDescriptor>(quote!(message Empty {})).unwrap();
+        assert_eq!(file.syntax, Syntax::Proto3);
+        assert_eq!(file.package, None);
+        assert_eq!(file.messages.len(), 1);
+
+        let file = syn::parse2::<ProtobufFileDescriptor>(quote!()).unwrap();
+        assert!(file.messages.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_file() {
+        let cases = [
+            (
+                quote!(syntax = "proto4";),
+                "unsupported syntax, expected \"proto2\" or \"proto3\"",
+            ),
+            (quote!(package a; package b;), "duplicate package statement"),
+            (
+                quote!(message A {} enum A { X = 0; }),
+                "duplicate type name",
+            ),
+        ];
+        for (input, expected) in cases {
+            let err = syn::parse2::<ProtobufFileDescriptor>(input).err().unwrap();
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_proto2_file() {
+        let input = quote!(
+            syntax = "proto2";
+
+            message SearchResponse {
+                required string query = 1 [default = "all"];
+                optional int32 page = 2 [default = -1];
+                repeated group Result = 3 {
+                    required string url = 4;
+                    optional string title = 5;
+                }
+                optional Status status = 6;
+            }
+
+            enum Status {
+                ACTIVE = 1;
+                DELETED = 2;
+            }
+        );
+        let file = syn::parse2::<ProtobufFileDescriptor>(input).unwrap();
+        assert_eq!(file.syntax, Syntax::Proto2);
+
+        let message = &file.messages[0];
+        assert!(message.declares("Result"));
+        assert_eq!(message.messages[0].fields.0.len(), 2);
+        let Field::Message(group) = &message.fields.0[2] else {
+            panic!("expected a group field");
+        };
+        assert_eq!(group.name, "result");
+        assert_eq!(group.ty.name, "Result");
+        assert_eq!(group.label, Some(Label::Repeated));
+        assert!(group.group);
+
+        let Field::Scalar(query) = &message.fields.0[0] else {
+            panic!("expected a scalar field");
+        };
+        assert_eq!(query.label, Some(Label::Required));
+        assert_eq!(
+            query.options.default,
+            Some(DefaultValue::String("all".to_string()))
+        );
+        assert_eq!(file.enums[0].default_value().name, "ACTIVE");
+    }
+
+    #[test]
+    fn test_syntax_conflicts() {
+        let cases = [
+            (
+                quote!(message A { required int32 a = 1; }),
+                "required fields are not allowed in proto3",
+            ),
+            (
+                quote!(message A { optional int32 a = 1 [default = 2]; }),
+                "default values are not allowed in proto3",
+            ),
+            (
+                quote!(message A { oneof o { int32 a = 1 [default = 2]; } }),
+                "default values are not allowed in proto3",
+            ),
+            (
+                quote!(message A { optional group B = 1 {} }),
+                "groups are not allowed in proto3, use a nested message",
+            ),
+            (
+                quote!(enum E { A = 1; }),
+                "the first enum value must be zero",
+            ),
+            (
+                quote!(syntax = "proto2"; message A { int32 a = 1; }),
+                "fields must be labeled `optional`, `required` or `repeated` in proto2",
+            ),
+            (
+                quote!(syntax = "proto2"; message A { optional group b = 1 {} }),
+                "group names must start with a capital letter",
+            ),
+            (
+                quote!(syntax = "proto2"; message A { optional group B = 1 {} message B {} }),
+                "duplicate nested type name",
+            ),
+            (
+                quote!(syntax = "proto2"; message A { repeated int32 a = 1 [default = 1]; }),
+                "repeated fields cannot have default values",
+            ),
+            (
+                quote!(syntax = "proto2"; message A { optional bool a = 1 [default = 1]; }),
+                "invalid default value for a `bool` field",
+            ),
+        ];
+        for (input, expected) in cases {
+            let err = syn::parse2::<ProtobufFileDescriptor>(input).err().unwrap();
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_errors_across_messages_are_aggregated() {
+        let input = quote!(
+            message A {
+                int32 a = 0;
+            }
+            message B {
+                string b = 1;
+            }
+            enum C {}
+            message B {}
+        );
+        let errors = syn::parse2::<ProtobufFileDescriptor>(input)
+            .err()
+            .unwrap()
+            .into_iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "field number must be at least 1",
+                "enum must have at least one value",
+                "duplicate type name",
+            ]
+        );
+    }
+}
@@ -1,18 +1,70 @@
+pub mod enums;
 pub mod error;
 mod fields;
+pub mod file;
+pub mod resolve;
+pub mod source;
 
-use crate::fields::utils::is_protobuf_reserve_key_word;
+use crate::fields::utils::{
+    is_protobuf_reserve_key_word, parse_label, parse_option, parse_statements, parse_tag,
+    peek_keyword, peek_option,
+};
+use enums::ProtobufEnumDescriptor;
 pub use fields::*;
+use file::Syntax;
+use message::MessageField;
+use options::FieldOptions;
 use syn::parse::{Parse, ParseStream};
+use type_ref::TypeRef;
+
+/// A top-level protobuf definition.
+pub enum ProtobufDescriptor {
+    Message(ProtobufMessageDescriptor),
+    Enum(ProtobufEnumDescriptor),
+}
+
+impl ProtobufDescriptor {
+    /// Parses a message or enum written in the given syntax.
+    pub fn parse_with_syntax(input: ParseStream, syntax: Syntax) -> syn::Result<Self> {
+        if input.peek(syn::Token![enum]) {
+            return Ok(Self::Enum(ProtobufEnumDescriptor::parse_with_syntax(
+                input, syntax,
+            )?));
+        }
+        Ok(Self::Message(ProtobufMessageDescriptor::parse_with_syntax(
+            input, syntax,
+        )?))
+    }
+}
+
+impl Parse for ProtobufDescriptor {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Self::parse_with_syntax(input, Syntax::Proto3)
+    }
+}
 
 #[allow(unused)]
 pub struct ProtobufMessageDescriptor {
     pub name: String,
     pub fields: Fields,
+    /// Messages declared inside the message.
+    pub messages: Vec<ProtobufMessageDescriptor>,
+    /// Enums declared inside the message.
+    pub enums: Vec<ProtobufEnumDescriptor>,
 }
 
-impl Parse for ProtobufMessageDescriptor {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
+impl ProtobufMessageDescriptor {
+    /// Whether a message or enum with the given name is declared inside this
+    /// message.
+    pub fn declares(&self, name: &str) -> bool {
+        self.messages.iter().any(|message| message.name == name)
+            || self.enums.iter().any(|e| e.name == name)
+    }
+}
+
+impl ProtobufMessageDescriptor {
+    /// Parses a message written in the given syntax.
+    pub fn parse_with_syntax(input: ParseStream, syntax: Syntax) -> syn::Result<Self> {
         let message_name = input.parse::<syn::Ident>()?;
         if message_name != "message" {
             return Err(syn::Error::new(input.span(), "expected message keyword"));
@@ -21,14 +73,104 @@ impl Parse for ProtobufMessageDescriptor {
         if is_protobuf_reserve_key_word(&name.to_string()) {
             return Err(syn::Error::new(input.span(), "reserved keyword"));
         }
+        Self::parse_body(&name, input, syntax)
+    }
+
+    /// Parses the braced fields and nested types of a message or group.
+    fn parse_body(name: &syn::Ident, input: ParseStream, syntax: Syntax) -> syn::Result<Self> {
         let content;
         syn::braced!(content in input);
-        let fields = content.parse::<Fields>()?;
-        Ok(Self {
+        let mut descriptor = Self {
             name: name.to_string(),
-            fields,
-        })
+            fields: Fields::default(),
+            messages: Vec::new(),
+            enums: Vec::new(),
+        };
+        parse_statements(&content, |content| {
+            let span = content.span();
+            if content.peek(syn::Token![enum]) {
+                let nested = ProtobufEnumDescriptor::parse_with_syntax(content, syntax)?;
+                if descriptor.declares(&nested.name) {
+                    return Err(syn::Error::new(span, "duplicate nested type name"));
+                }
+                descriptor.enums.push(nested);
+            } else if peek_keyword(content, "message") {
+                let nested = Self::parse_with_syntax(content, syntax)?;
+                if descriptor.declares(&nested.name) {
+                    return Err(syn::Error::new(span, "duplicate nested type name"));
+                }
+                descriptor.messages.push(nested);
+            } else if peek_option(content) {
+                parse_option(content)?;
+            } else if peek_group(content) {
+                let (field, nested) = parse_group(content, syntax)?;
+                if descriptor.declares(&nested.name) {
+                    return Err(syn::Error::new(span, "duplicate nested type name"));
+                }
+                descriptor.fields.add(span, Field::Message(field), syntax)?;
+                descriptor.messages.push(nested);
+            } else {
+                descriptor.fields.parse_field(content, syntax)?;
+            }
+            Ok(())
+        })?;
+        Ok(descriptor)
+    }
+}
+
+impl Parse for ProtobufMessageDescriptor {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Self::parse_with_syntax(input, Syntax::Proto3)
+    }
+}
+
+/// Whether the next tokens start a group, such as `optional group Result`.
+fn peek_group(input: ParseStream) -> bool {
+    let fork = input.fork();
+    parse_label(&fork).is_ok() && peek_keyword(&fork, "group")
+}
+
+/// Parses a proto2 group, such as `repeated group Result = 1 { ... }`. A
+/// group declares a nested message together with a field of that type, named
+/// after the message in lowercase.
+fn parse_group(
+    input: ParseStream,
+    syntax: Syntax,
+) -> syn::Result<(MessageField, ProtobufMessageDescriptor)> {
+    let label = parse_label(input)?;
+    input.parse::<syn::Ident>()?;
+    let name = input.parse::<syn::Ident>()?;
+    if !name.to_string().starts_with(char::is_uppercase) {
+        return Err(syn::Error::new(
+            name.span(),
+            "group names must start with a capital letter",
+        ));
     }
+    input.parse::<syn::Token![=]>()?;
+    let tag = parse_tag(input)?;
+    let options_span = input.span();
+    let options = input.parse::<FieldOptions>()?;
+    options.check_packable(options_span, false)?;
+    if options.default.is_some() {
+        return Err(syn::Error::new(
+            options_span,
+            "groups cannot have default values",
+        ));
+    }
+    let message = ProtobufMessageDescriptor::parse_body(&name, input, syntax)?;
+
+    let field = MessageField {
+        name: message.name.to_lowercase(),
+        ty: TypeRef {
+            name: message.name.clone(),
+            span: name.span(),
+        },
+        label,
+        tag,
+        options,
+        group: true,
+    };
+    Ok((field, message))
 }
 
 #[cfg(test)]
@@ -50,4 +192,112 @@ mod tests {
         assert_eq!(message.name, "TestMessage");
         assert_eq!(message.fields.0.len(), 4);
     }
+
+    #[test]
+    pub fn test_parse_nested_enum() {
+        let input = quote!(
+            message TestMessage {
+                Status status = 1;
+                enum Status {
+                    UNKNOWN = 0;
+                    ACTIVE = 1;
+                }
+                string name = 2;
+            }
+        );
+        let message = syn::parse2::<ProtobufMessageDescriptor>(input).unwrap();
+        assert_eq!(message.fields.0.len(), 2);
+        assert_eq!(message.enums.len(), 1);
+        assert_eq!(message.enums[0].name, "Status");
+    }
+
+    #[test]
+    pub fn test_parse_nested_message() {
+        let input = quote!(
+            message Outer {
+                Inner inner = 1;
+                message Inner {
+                    Kind kind = 1;
+                    message Leaf {}
+                    enum Kind {
+                        A = 0;
+                    }
+                }
+            }
+        );
+        let message = syn::parse2::<ProtobufMessageDescriptor>(input).unwrap();
+        assert_eq!(message.fields.0.len(), 1);
+        assert_eq!(message.messages.len(), 1);
+        assert!(message.declares("Inner"));
+        assert!(!message.declares("Leaf"));
+
+        let inner = &message.messages[0];
+        assert_eq!(inner.name, "Inner");
+        assert_eq!(inner.fields.0.len(), 1);
+        assert!(inner.declares("Leaf"));
+        assert!(inner.declares("Kind"));
+    }
+
+    #[test]
+    pub fn test_duplicate_nested_name() {
+        let input = quote!(
+            message Outer {
+                message Inner {}
+                enum Inner {
+                    A = 0;
+                }
+            }
+        );
+        let err = syn::parse2::<ProtobufMessageDescriptor>(input)
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "duplicate nested type name");
+    }
+
+    #[test]
+    pub fn test_nested_errors_are_aggregated() {
+        let input = quote!(
+            message Outer {
+                int32 a = 0;
+                message Inner {
+                    unit32 b = 1;
+                    enum Kind {
+                        A = 1;
+                    }
+                }
+                string c = 2;
+                bool c2 = 2;
+            }
+        );
+        let errors = syn::parse2::<ProtobufMessageDescriptor>(input)
+            .err()
+            .unwrap()
+            .into_iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "field number must be at least 1",
+                "unknown type `unit32`, did you mean `uint32`?",
+                "the first enum value must be zero",
+                "duplicate tag",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_parse_descriptor() {
+        let input = quote!(enum Status { UNKNOWN = 0; });
+        assert!(matches!(
+            syn::parse2::<ProtobufDescriptor>(input).unwrap(),
+            ProtobufDescriptor::Enum(..)
+        ));
+
+        let input = quote!(message Empty {});
+        assert!(matches!(
+            syn::parse2::<ProtobufDescriptor>(input).unwrap(),
+            ProtobufDescriptor::Message(..)
+        ));
+    }
 }
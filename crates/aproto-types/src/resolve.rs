@@ -0,0 +1,586 @@
+use crate::{
+    enums::ProtobufEnumDescriptor,
+    file::ProtobufFileDescriptor,
+    map::ValueTy,
+    options::DefaultValue,
+    type_ref::TypeRef,
+    utils::{combine, suggest},
+    Field, Label, ProtobufMessageDescriptor,
+};
+
+/// Whether a type reference names a message or an enum. Enums are encoded as
+/// varints while messages are length-delimited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TypeKind {
+    Message,
+    Enum,
+}
+
+/// A type reference resolved to the declaration it names.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedType {
+    /// The names of the messages the type is declared in, outermost first.
+    pub parents: Vec<String>,
+    pub name: String,
+    pub kind: TypeKind,
+}
+
+/// The declarations visible from a message: the types nested in it, in the
+/// messages enclosing it, and at the top level of the file.
+#[derive(Clone)]
+pub struct Resolver<'a> {
+    file: &'a ProtobufFileDescriptor,
+    /// Outermost message first.
+    messages: Vec<&'a ProtobufMessageDescriptor>,
+}
+
+impl<'a> Resolver<'a> {
+    /// A resolver for the top level of the file.
+    pub fn new(file: &'a ProtobufFileDescriptor) -> Self {
+        Self {
+            file,
+            messages: Vec::new(),
+        }
+    }
+
+    /// A resolver for the scope of a message declared in the current one.
+    pub fn nested(&self, message: &'a ProtobufMessageDescriptor) -> Self {
+        let mut messages = self.messages.clone();
+        messages.push(message);
+        Self {
+            file: self.file,
+            messages,
+        }
+    }
+
+    pub fn file(&self) -> &'a ProtobufFileDescriptor {
+        self.file
+    }
+
+    /// The messages enclosing the scope, outermost first.
+    pub fn messages(&self) -> &[&'a ProtobufMessageDescriptor] {
+        &self.messages
+    }
+
+    /// Resolves a type name the way protobuf does. A fully qualified name,
+    /// starting with a dot, is looked up from the root. Otherwise the first
+    /// part of a dotted name is searched for from the innermost message
+    /// outwards, through the file's package to the root, and the rest of the
+    /// name must then be declared inside whatever it names.
+    pub fn resolve(&self, name: &str) -> Option<ResolvedType> {
+        self.find(name).map(|(resolved, _)| resolved)
+    }
+
+    /// Resolves a type name, together with the file declaring it.
+    fn find(&self, name: &str) -> Option<(ResolvedType, &'a ProtobufFileDescriptor)> {
+        let segments = name.trim_start_matches('.').split('.').collect::<Vec<_>>();
+        if name.starts_with('.') {
+            return self.lookup(&segments);
+        }
+
+        let scope = package(self.file)
+            .chain(self.messages.iter().map(|message| message.name.as_str()))
+            .collect::<Vec<_>>();
+        for i in (0..=scope.len()).rev() {
+            let mut path = scope[..i].to_vec();
+            path.push(segments[0]);
+            let names_package = self
+                .files()
+                .any(|file| package(file).collect::<Vec<_>>().starts_with(&path));
+            if names_package || self.lookup(&path).is_some() {
+                path.extend(&segments[1..]);
+                return self.lookup(&path);
+            }
+        }
+        None
+    }
+
+    /// Looks up a fully qualified name, given as its dot-separated parts, in
+    /// the file and the files it imports.
+    fn lookup(&self, path: &[&str]) -> Option<(ResolvedType, &'a ProtobufFileDescriptor)> {
+        self.files().find_map(|file| {
+            let package = package(file).collect::<Vec<_>>();
+            let (name, parents) = path.strip_prefix(package.as_slice())?.split_last()?;
+
+            let (messages, enums) = declared_in(file, parents)?;
+            let kind = if messages.iter().any(|m| m.name == *name) {
+                TypeKind::Message
+            } else if enums.iter().any(|e| e.name == *name) {
+                TypeKind::Enum
+            } else {
+                return None;
+            };
+            let resolved = ResolvedType {
+                parents: parents.iter().map(|parent| parent.to_string()).collect(),
+                name: name.to_string(),
+                kind,
+            };
+            Some((resolved, file))
+        })
+    }
+
+    /// Resolves a type name to the enum it names, if it names one.
+    pub fn resolve_enum(&self, name: &str) -> Option<&'a ProtobufEnumDescriptor> {
+        let (resolved, file) = self.find(name)?;
+        let (_, enums) = declared_in(file, &resolved.parents)?;
+        enums.iter().find(|e| e.name == resolved.name)
+    }
+
+    /// The file followed by the files it imports.
+    fn files(&self) -> impl Iterator<Item = &'a ProtobufFileDescriptor> {
+        std::iter::once(self.file).chain(&self.file.dependencies)
+    }
+
+    /// The names of every type visible from the scope, innermost first.
+    pub fn visible_names(&self) -> impl Iterator<Item = &str> {
+        let nested = self.messages.iter().rev().flat_map(|message| {
+            let messages = message.messages.iter().map(|m| m.name.as_str());
+            messages.chain(message.enums.iter().map(|e| e.name.as_str()))
+        });
+        let top_level = self.files().flat_map(|file| {
+            let messages = file.messages.iter().map(|m| m.name.as_str());
+            messages.chain(file.enums.iter().map(|e| e.name.as_str()))
+        });
+        nested.chain(top_level)
+    }
+
+    /// A resolver for the scope of the message a type name refers to, if it
+    /// refers to one.
+    pub fn message_scope(&self, name: &str) -> Option<Self> {
+        let (resolved, file) = self.find(name)?;
+        if resolved.kind != TypeKind::Message {
+            return None;
+        }
+        let mut declared = file.messages.as_slice();
+        let mut messages = Vec::new();
+        for name in resolved.parents.iter().chain([&resolved.name]) {
+            let message = declared.iter().find(|m| m.name == *name)?;
+            messages.push(message);
+            declared = &message.messages;
+        }
+        Some(Self {
+            file: self.file,
+            messages,
+        })
+    }
+
+    /// Whether a value of the message a type name refers to can contain a
+    /// value of the current message, through singular message fields and
+    /// oneof members. A field of that type must then be boxed, otherwise the
+    /// structs on the cycle would have infinite size.
+    pub fn is_recursive(&self, name: &str) -> bool {
+        let current = self.messages.last().copied();
+        let mut visited = Vec::new();
+        let mut pending = self.message_scope(name).into_iter().collect::<Vec<_>>();
+        while let Some(scope) = pending.pop() {
+            let message = *scope.messages.last().unwrap();
+            if current.is_some_and(|current| std::ptr::eq(current, message)) {
+                return true;
+            }
+            if visited
+                .iter()
+                .any(|visited| std::ptr::eq(*visited, message))
+            {
+                continue;
+            }
+            visited.push(message);
+            let mut types = Vec::new();
+            singular_message_types(&message.fields.0, &mut types);
+            pending.extend(types.iter().filter_map(|ty| scope.message_scope(ty)));
+        }
+        false
+    }
+}
+
+/// Collects the types of the singular message fields among `fields`,
+/// including oneof members. Repeated and map fields are stored on the heap.
+fn singular_message_types<'a>(fields: &'a [Field], types: &mut Vec<&'a str>) {
+    for field in fields {
+        match field {
+            Field::Message(field) if field.label != Some(Label::Repeated) => {
+                types.push(&field.ty.name)
+            }
+            Field::Oneof(oneof) => singular_message_types(&oneof.fields, types),
+            _ => {}
+        }
+    }
+}
+
+/// The messages and enums declared in the message with the given parents and
+/// name, or at the top level of the file when there are none.
+fn declared_in<'a>(
+    file: &'a ProtobufFileDescriptor,
+    messages: &[impl AsRef<str>],
+) -> Option<(
+    &'a [ProtobufMessageDescriptor],
+    &'a [ProtobufEnumDescriptor],
+)> {
+    let mut declared = (file.messages.as_slice(), file.enums.as_slice());
+    for name in messages {
+        let message = declared.0.iter().find(|m| m.name == name.as_ref())?;
+        declared = (&message.messages, &message.enums);
+    }
+    Some(declared)
+}
+
+/// The dot-separated parts of a file's package.
+fn package(file: &ProtobufFileDescriptor) -> impl Iterator<Item = &str> {
+    file.package.iter().flat_map(|package| package.split('.'))
+}
+
+/// Checks that every type referenced by a field in the file is declared in
+/// it or in a file it imports. All unknown types are reported, each at the
+/// reference, together with the closest visible name when there is one.
+pub fn check(file: &ProtobufFileDescriptor) -> syn::Result<()> {
+    combine(
+        file.messages
+            .iter()
+            .filter_map(|message| check_message(file, message).err()),
+    )
+}
+
+/// Checks the types referenced by one of the top-level messages of the file,
+/// see [`check`].
+pub fn check_message(
+    file: &ProtobufFileDescriptor,
+    message: &ProtobufMessageDescriptor,
+) -> syn::Result<()> {
+    let mut errors = Vec::new();
+    check_nested(&Resolver::new(file).nested(message), &mut errors);
+    combine(errors)
+}
+
+fn check_nested(resolver: &Resolver, errors: &mut Vec<syn::Error>) {
+    let message = resolver.messages.last().unwrap();
+    for field in &message.fields.0 {
+        check_field(resolver, field, errors);
+    }
+    for nested in &message.messages {
+        check_nested(&resolver.nested(nested), errors);
+    }
+}
+
+fn check_field(resolver: &Resolver, field: &Field, errors: &mut Vec<syn::Error>) {
+    let mut check = |ty: &TypeRef, default: Option<&DefaultValue>, packed: bool| {
+        let message = match (resolver.resolve(&ty.name), default) {
+            (None, _) => match suggest(&ty.name, resolver.visible_names()) {
+                Some(name) => format!("unknown type `{}`, did you mean `{name}`?", ty.name),
+                None => format!("unknown type `{}`", ty.name),
+            },
+            (Some(resolved), _) if resolved.kind == TypeKind::Message && packed => {
+                "[packed] can only be specified for repeated numeric fields".to_string()
+            }
+            (Some(resolved), Some(_)) if resolved.kind == TypeKind::Message => {
+                "only scalar and enum fields can have default values".to_string()
+            }
+            (Some(_), Some(default)) => {
+                let e = resolver.resolve_enum(&ty.name).unwrap();
+                match default {
+                    DefaultValue::Ident(value) if e.value(value).is_some() => return,
+                    DefaultValue::Ident(value) => {
+                        format!("unknown value `{value}` for enum `{}`", e.name)
+                    }
+                    _ => format!(
+                        "the default of an enum field must be a value of `{}`",
+                        e.name
+                    ),
+                }
+            }
+            (Some(_), None) => return,
+        };
+        errors.push(syn::Error::new(ty.span, message));
+    };
+    match field {
+        Field::Message(field) => check(
+            &field.ty,
+            field.options.default.as_ref(),
+            field.options.packed.is_some(),
+        ),
+        Field::Map(field) => {
+            if let ValueTy::Message(ty) = &field.value_ty {
+                check(ty, None, false);
+            }
+        }
+        Field::Oneof(oneof) => {
+            for field in &oneof.fields {
+                check_field(resolver, field, errors);
+            }
+        }
+        Field::Scalar(..) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::quote;
+
+    #[test]
+    fn test_resolve() {
+        let file = syn::parse2::<ProtobufFileDescriptor>(quote!(
+            message Outer {
+                message Inner {
+                    message Leaf {}
+                }
+                enum Kind {
+                    A = 0;
+                }
+            }
+            enum Status {
+                UNKNOWN = 0;
+            }
+        ))
+        .unwrap();
+        let outer = Resolver::new(&file).nested(&file.messages[0]);
+        let inner = outer.nested(&file.messages[0].messages[0]);
+
+        let resolved = inner.resolve("Leaf").unwrap();
+        assert_eq!(resolved.parents, ["Outer", "Inner"]);
+        assert_eq!(resolved.kind, TypeKind::Message);
+
+        let resolved = inner.resolve("Kind").unwrap();
+        assert_eq!(resolved.parents, ["Outer"]);
+        assert_eq!(resolved.kind, TypeKind::Enum);
+
+        let resolved = inner.resolve("Status").unwrap();
+        assert!(resolved.parents.is_empty());
+        assert_eq!(resolved.kind, TypeKind::Enum);
+
+        assert_eq!(inner.resolve("Outer").unwrap().kind, TypeKind::Message);
+        assert!(outer.resolve("Leaf").is_none());
+        assert!(inner.resolve("Missing").is_none());
+    }
+
+    #[test]
+    fn test_is_recursive() {
+        let file = syn::parse2::<ProtobufFileDescriptor>(quote!(
+            message A {
+                B b = 1;
+                C c = 2;
+                message Inner {
+                    oneof o {
+                        A a = 1;
+                    }
+                }
+                Inner inner = 3;
+            }
+            message B {
+                A a = 1;
+            }
+            message C {
+                repeated A a = 1;
+                map<string, A> m = 2;
+                Kind kind = 3;
+            }
+            enum Kind {
+                UNKNOWN = 0;
+            }
+        ))
+        .unwrap();
+        let a = Resolver::new(&file).nested(&file.messages[0]);
+        let inner = a.nested(&file.messages[0].messages[0]);
+        let c = Resolver::new(&file).nested(&file.messages[2]);
+
+        assert!(a.is_recursive("A"));
+        assert!(a.is_recursive("B"));
+        assert!(a.is_recursive("Inner"));
+        assert!(inner.is_recursive("A"));
+        // `C` only holds `A` on the heap
+        assert!(!a.is_recursive("C"));
+        assert!(!c.is_recursive("Kind"));
+        assert!(!a.is_recursive("Missing"));
+    }
+
+    #[test]
+    fn test_resolve_dotted() {
+        let file = syn::parse2::<ProtobufFileDescriptor>(quote!(
+            package foo.bar;
+            message Outer {
+                message Inner {
+                    message Leaf {}
+                }
+                enum Kind {
+                    A = 0;
+                }
+            }
+            message Other {
+                message Inner {}
+            }
+        ))
+        .unwrap();
+        let outer = Resolver::new(&file).nested(&file.messages[0]);
+        let inner = outer.nested(&file.messages[0].messages[0]);
+        let other = Resolver::new(&file).nested(&file.messages[1]);
+
+        let resolved = outer.resolve("Inner.Leaf").unwrap();
+        assert_eq!(resolved.parents, ["Outer", "Inner"]);
+        assert_eq!(resolved.name, "Leaf");
+
+        for name in [
+            "Outer.Kind",
+            "bar.Outer.Kind",
+            "foo.bar.Outer.Kind",
+            ".foo.bar.Outer.Kind",
+        ] {
+            let resolved = inner.resolve(name).unwrap();
+            assert_eq!(resolved.parents, ["Outer"]);
+            assert_eq!(resolved.kind, TypeKind::Enum);
+        }
+
+        // `Inner` is found in `Other` first, so `Inner.Leaf` is not looked for
+        // in `Outer.Inner`
+        assert!(other.resolve("Inner.Leaf").is_none());
+        assert_eq!(other.resolve("Outer.Inner.Leaf").unwrap().name, "Leaf");
+        assert_eq!(other.resolve("Inner").unwrap().parents, ["Other"]);
+
+        assert!(inner.resolve(".Outer.Kind").is_none());
+        assert!(inner.resolve(".foo.Outer").is_none());
+        assert!(inner.resolve("baz.Outer").is_none());
+        assert!(inner.resolve("Outer.Missing").is_none());
+    }
+
+    #[test]
+    fn test_resolve_imported() {
+        let mut file = syn::parse2::<ProtobufFileDescriptor>(quote!(
+            package app;
+            message User {}
+        ))
+        .unwrap();
+        file.dependencies = vec![
+            syn::parse2(quote!(
+                package app;
+                enum Role {
+                    NONE = 0;
+                }
+            ))
+            .unwrap(),
+            syn::parse2(quote!(
+                package common.v1;
+                message Money {
+                    enum Currency {
+                        EUR = 0;
+                    }
+                }
+            ))
+            .unwrap(),
+        ];
+        let user = Resolver::new(&file).nested(&file.messages[0]);
+
+        assert_eq!(user.resolve("Role").unwrap().kind, TypeKind::Enum);
+        assert_eq!(user.resolve("app.Role").unwrap().kind, TypeKind::Enum);
+        assert_eq!(
+            user.resolve("common.v1.Money").unwrap().kind,
+            TypeKind::Message
+        );
+        assert_eq!(
+            user.resolve_enum(".common.v1.Money.Currency").unwrap().name,
+            "Currency"
+        );
+        assert!(user.resolve("Money").is_none());
+        assert_eq!(
+            user.visible_names().collect::<Vec<_>>(),
+            ["User", "Role", "Money"]
+        );
+    }
+
+    #[test]
+    fn test_check_unknown_types() {
+        let file = syn::parse2::<ProtobufFileDescriptor>(quote!(
+            message Person {
+                Adress address = 1;
+                map<string, Rol> roles = 2;
+                oneof contact {
+                    Emial email = 3;
+                }
+                message Address {}
+            }
+        ))
+        .unwrap();
+        let errors = check(&file)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "unknown type `Adress`, did you mean `Address`?",
+                "unknown type `Rol`",
+                "unknown type `Emial`"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_defaults() {
+        let file = syn::parse2::<ProtobufFileDescriptor>(quote!(
+            syntax = "proto2";
+            message Person {
+                optional Status status = 1 [default = ACTIVE];
+                optional Status other = 2 [default = DELETED];
+                optional Status third = 3 [default = 1];
+                optional Person parent = 4 [default = ACTIVE];
+            }
+            enum Status {
+                UNKNOWN = 0;
+                ACTIVE = 1;
+            }
+        ))
+        .unwrap();
+        let errors = check(&file)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "unknown value `DELETED` for enum `Status`",
+                "the default of an enum field must be a value of `Status`",
+                "only scalar and enum fields can have default values",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_known_types() {
+        let file = syn::parse2::<ProtobufFileDescriptor>(quote!(
+            message Person {
+                Address address = 1;
+                Status status = 2;
+                message Address {
+                    Person owner = 1;
+                    Status status = 2;
+                }
+            }
+            enum Status {
+                UNKNOWN = 0;
+            }
+        ))
+        .unwrap();
+        assert!(check(&file).is_ok());
+    }
+
+    #[test]
+    fn test_check_packed() {
+        let file = syn::parse2::<ProtobufFileDescriptor>(quote!(
+            message Person {
+                repeated Status history = 1 [packed = true];
+                repeated Person children = 2 [packed = true];
+            }
+            enum Status {
+                UNKNOWN = 0;
+            }
+        ))
+        .unwrap();
+        let errors = check(&file)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            ["[packed] can only be specified for repeated numeric fields"]
+        );
+    }
+}
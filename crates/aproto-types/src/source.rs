@@ -0,0 +1,321 @@
+use std::{fmt, iter::Peekable, str::Chars};
+
+use proc_macro2::{Literal, TokenStream};
+
+/// A position in the text of a file, counting lines and columns from one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A top-level statement of a file, such as an import or a message, and
+/// where it starts.
+pub struct Statement {
+    pub tokens: TokenStream,
+    pub location: Location,
+}
+
+/// An error in the text of a file, which could not be tokenized.
+#[derive(Debug)]
+pub struct SourceError {
+    pub location: Location,
+    pub message: String,
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Turns the text of a `.proto` file into the tokens the parser reads, so
+/// that a file on disk produces the same descriptors as one written inside a
+/// macro invocation.
+///
+/// Rust's tokenizer cannot read protobuf text as it is: comments are dropped
+/// here, and string literals, which may be single quoted and use C escapes,
+/// are rewritten as Rust string literals, or as byte string literals when
+/// they are not valid UTF-8.
+///
+/// The tokens are split into the file's top-level statements, each with the
+/// location it starts at, as tokens parsed from a string carry no location
+/// to report errors at.
+pub fn tokenize(source: &str) -> Result<Vec<Statement>, SourceError> {
+    let mut cursor = Cursor {
+        chars: source.chars().peekable(),
+        location: Location { line: 1, column: 1 },
+    };
+    let mut statements = Vec::new();
+    let mut text = String::new();
+    let mut start = None;
+    // A statement ends at a `;` or at the brace closing its body, unless it
+    // assigns a value, such as an option set to `{ ... }`
+    let mut depth = 0usize;
+    let mut assignment = false;
+    loop {
+        let location = cursor.location;
+        let Some(c) = cursor.next() else {
+            break;
+        };
+        match c {
+            '/' if cursor.chars.peek() == Some(&'/') => {
+                while cursor.next_if(|c| c != '\n').is_some() {}
+                text.push(' ');
+                continue;
+            }
+            '/' if cursor.chars.peek() == Some(&'*') => {
+                cursor.next();
+                let mut previous = None;
+                loop {
+                    match cursor.next() {
+                        Some('/') if previous == Some('*') => break,
+                        Some(c) => previous = Some(c),
+                        None => return Err(error(location, "unterminated comment")),
+                    }
+                }
+                text.push(' ');
+                continue;
+            }
+            c if c.is_whitespace() => {
+                text.push(c);
+                continue;
+            }
+            _ => {}
+        }
+
+        let statement_start = *start.get_or_insert(location);
+        let end = match c {
+            '"' | '\'' => {
+                let bytes = parse_string(&mut cursor, c).map_err(|m| error(location, m))?;
+                let literal = match String::from_utf8(bytes) {
+                    Ok(value) => Literal::string(&value),
+                    Err(error) => Literal::byte_string(error.as_bytes()),
+                };
+                text.push_str(&literal.to_string());
+                false
+            }
+            '{' => {
+                depth += 1;
+                text.push(c);
+                false
+            }
+            '}' => {
+                depth = depth.saturating_sub(1);
+                text.push(c);
+                depth == 0 && !assignment
+            }
+            '=' if depth == 0 => {
+                assignment = true;
+                text.push(c);
+                false
+            }
+            ';' => {
+                text.push(c);
+                depth == 0
+            }
+            c => {
+                text.push(c);
+                false
+            }
+        };
+        if end {
+            statements.push(statement(&text, statement_start)?);
+            text.clear();
+            start = None;
+            assignment = false;
+        }
+    }
+    statements.extend(start.map(|start| statement(&text, start)).transpose()?);
+    Ok(statements)
+}
+
+/// The characters of a file, with the location of the next one.
+struct Cursor<'a> {
+    chars: Peekable<Chars<'a>>,
+    location: Location,
+}
+
+impl Cursor<'_> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.location.line += 1;
+            self.location.column = 1;
+        } else {
+            self.location.column += 1;
+        }
+        Some(c)
+    }
+
+    fn next_if(&mut self, f: impl FnOnce(char) -> bool) -> Option<char> {
+        match self.chars.peek() {
+            Some(&c) if f(c) => self.next(),
+            _ => None,
+        }
+    }
+}
+
+fn statement(text: &str, location: Location) -> Result<Statement, SourceError> {
+    let tokens = text
+        .parse::<TokenStream>()
+        .map_err(|err| error(location, format!("invalid token: {err}")))?;
+    Ok(Statement { tokens, location })
+}
+
+/// Parses the rest of a string literal opened by `quote` into the bytes it
+/// holds, resolving its escapes.
+fn parse_string(cursor: &mut Cursor, quote: char) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    loop {
+        let c = match cursor.next() {
+            Some('\n') | None => return Err("unterminated string literal".to_string()),
+            Some(c) if c == quote => break,
+            Some('\\') => cursor
+                .next()
+                .ok_or_else(|| "unterminated string literal".to_string())?,
+            Some(c) => {
+                bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                continue;
+            }
+        };
+        match c {
+            'a' => bytes.push(0x07),
+            'b' => bytes.push(0x08),
+            'f' => bytes.push(0x0c),
+            'n' => bytes.push(b'\n'),
+            'r' => bytes.push(b'\r'),
+            't' => bytes.push(b'\t'),
+            'v' => bytes.push(0x0b),
+            '\\' | '\'' | '"' | '?' => bytes.push(c as u8),
+            'x' | 'X' => bytes.push(parse_digits(cursor, 16, 2)? as u8),
+            '0'..='7' => {
+                let mut value = c.to_digit(8).unwrap();
+                for _ in 0..2 {
+                    match cursor.next_if(|c| c.is_digit(8)) {
+                        Some(c) => value = value * 8 + c.to_digit(8).unwrap(),
+                        None => break,
+                    }
+                }
+                bytes.push(u8::try_from(value).map_err(|_| "octal escape out of range")?);
+            }
+            'u' | 'U' => {
+                let digits = if c == 'u' { 4 } else { 8 };
+                let c = char::from_u32(parse_digits(cursor, 16, digits)?)
+                    .ok_or("invalid unicode escape")?;
+                bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            c => return Err(format!("unknown escape `\\{c}`")),
+        }
+    }
+    Ok(bytes)
+}
+
+/// Parses up to `max` digits in the given radix, requiring at least one.
+fn parse_digits(cursor: &mut Cursor, radix: u32, max: usize) -> Result<u32, String> {
+    let mut value = None;
+    for _ in 0..max {
+        match cursor.next_if(|c| c.is_digit(radix)) {
+            Some(c) => value = Some(value.unwrap_or(0) * radix + c.to_digit(radix).unwrap()),
+            None => break,
+        }
+    }
+    value.ok_or_else(|| "expected a digit in escape".to_string())
+}
+
+fn error(location: Location, message: impl Into<String>) -> SourceError {
+    SourceError {
+        location,
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{file::ProtobufFileDescriptor, options::DefaultValue};
+
+    #[test]
+    fn test_tokenize() {
+        let source = r#"
+            // A person.
+            syntax = 'proto2'; /* block
+                comment */
+            message Person {
+                optional string name = 1 [default = "a\tb\x41\101\"/*"]; // trailing
+                optional bytes data = 2 [default = "\377\0a"];
+            }
+        "#;
+        let tokens = tokenize(source)
+            .unwrap()
+            .into_iter()
+            .map(|statement| statement.tokens)
+            .collect::<TokenStream>();
+        let file = syn::parse2::<ProtobufFileDescriptor>(tokens).unwrap();
+        let defaults = file.messages[0]
+            .fields
+            .0
+            .iter()
+            .map(|field| field.options().unwrap().default.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            defaults,
+            [
+                Some(DefaultValue::String("a\tbAA\"/*".to_string())),
+                Some(DefaultValue::Bytes(vec![0xff, 0x00, b'a'])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_statements() {
+        let source = "syntax = \"proto3\";\n\
+            option (a) = { b: 1 };\n\
+            \n  message A { message B {} }\n\
+            service S { rpc M (A) returns (A); } enum E { X = 0; }\n\
+            message C";
+        let statements = tokenize(source)
+            .unwrap()
+            .into_iter()
+            .map(|statement| (statement.location.to_string(), statement.tokens.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statements,
+            [
+                ("1:1", "syntax = \"proto3\" ;"),
+                ("2:1", "option (a) = { b : 1 } ;"),
+                ("4:3", "message A { message B { } }"),
+                ("5:1", "service S { rpc M (A) returns (A) ; }"),
+                ("5:38", "enum E { X = 0 ; }"),
+                ("6:1", "message C"),
+            ]
+            .map(|(location, tokens)| (location.to_string(), tokens.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_tokenize_errors() {
+        let cases = [
+            ("message A {} /* open", "1:14: unterminated comment"),
+            ("\"open\nmessage A {}", "1:1: unterminated string literal"),
+            (
+                "message A {\n  string a = 1 [default = \"\\q\"];",
+                "2:27: unknown escape `\\q`",
+            ),
+            (r#""\777""#, "1:1: octal escape out of range"),
+            (
+                "message A {\n  ) }",
+                "1:1: invalid token: cannot parse string into token stream",
+            ),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(tokenize(source).err().unwrap().to_string(), expected);
+        }
+    }
+}
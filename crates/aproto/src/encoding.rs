@@ -1,26 +1,161 @@
-use bytes::BufMut;
+use ::bytes::{Buf, BufMut, Bytes};
+use aproto_types::error::AprotoError;
+use std::cell::Cell;
 
 use crate::wire_type::WireType;
 
 pub mod varint;
-pub use varint::{encode_varint, encoded_len};
+pub use varint::{decode_varint, encode_varint, encoded_len};
 
-pub const MIN_TAG: u32 = 1;
-pub const MAX_TAG: u32 = (1 << 29) - 1;
+pub use aproto_types::{MAX_TAG, MIN_TAG};
 
 #[allow(unused)]
 pub fn encode_tag(tag: u32, wire_type: WireType, buf: &mut impl BufMut) {
     debug_assert!((MIN_TAG..=MAX_TAG).contains(&tag));
-    let tag_and_wire_type = (tag << 4) | wire_type as u32;
+    let tag_and_wire_type = (tag << 3) | wire_type as u32;
     encode_varint(u64::from(tag_and_wire_type), buf);
 }
 
+/// Decodes a field key into its field number and wire type.
+///
+/// Field numbers outside `MIN_TAG..=MAX_TAG` are rejected.
+#[inline]
+pub fn decode_key(buf: &mut impl Buf) -> Result<(u32, WireType), AprotoError> {
+    let key = decode_varint(buf)?;
+    let wire_type = WireType::try_from(key & 0x07)?;
+    let tag = key >> 3;
+    if !(u64::from(MIN_TAG)..=u64::from(MAX_TAG)).contains(&tag) {
+        return Err(AprotoError::InvalidTag(tag));
+    }
+    Ok((tag as u32, wire_type))
+}
+
 #[allow(unused)]
 #[inline]
 pub fn tag_len(tag: u32) -> usize {
     varint::encoded_len(u64::from(tag << 3))
 }
 
+/// Checks that a field was encoded with the wire type its declared type expects.
+#[inline]
+pub fn check_wire_type(expected: WireType, actual: WireType) -> Result<(), AprotoError> {
+    if expected != actual {
+        return Err(AprotoError::UnexpectedWireType {
+            expected: expected as u8,
+            actual: actual as u8,
+        });
+    }
+    Ok(())
+}
+
+/// Decodes the length prefix of a length-delimited value, checking that the
+/// value itself fits in the remaining buffer.
+#[inline]
+pub fn decode_length_delimiter(buf: &mut impl Buf) -> Result<usize, AprotoError> {
+    let len = decode_varint(buf)?;
+    if len > buf.remaining() as u64 {
+        return Err(AprotoError::BufferUnderflow);
+    }
+    Ok(len as usize)
+}
+
+/// Decodes a repeated field of a packable type into `values`.
+///
+/// Accepts both a single unpacked element and a packed run of elements, as
+/// parsers must handle either form regardless of how the field is declared.
+pub fn decode_packable<T: Default, B: Buf>(
+    wire_type: WireType,
+    element_wire_type: WireType,
+    values: &mut Vec<T>,
+    buf: &mut B,
+    decode: impl Fn(WireType, &mut T, &mut B) -> Result<(), AprotoError>,
+) -> Result<(), AprotoError> {
+    if wire_type != WireType::LengthDelimited {
+        let mut value = T::default();
+        decode(wire_type, &mut value, buf)?;
+        values.push(value);
+        return Ok(());
+    }
+
+    let len = decode_length_delimiter(buf)?;
+    let end = buf.remaining() - len;
+    while buf.remaining() > end {
+        let mut value = T::default();
+        decode(element_wire_type, &mut value, buf)?;
+        values.push(value);
+    }
+
+    if buf.remaining() != end {
+        return Err(AprotoError::DelimitedLengthExceeded);
+    }
+    Ok(())
+}
+
+/// How deeply messages and groups may nest in decoded input. Deeper input is
+/// rejected, as decoding it could overflow the stack.
+pub const RECURSION_LIMIT: u32 = 100;
+
+thread_local! {
+    /// The number of nested messages and groups being decoded on the thread.
+    static DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Runs `decode` for a nested message or group, one level deeper than the
+/// value it is found in.
+///
+/// Fails with `RecursionLimitExceeded` once `RECURSION_LIMIT` levels are
+/// being decoded.
+fn decode_nested<T>(decode: impl FnOnce() -> Result<T, AprotoError>) -> Result<T, AprotoError> {
+    /// Leaves the level again, also when `decode` panics.
+    struct Level;
+
+    impl Drop for Level {
+        fn drop(&mut self) {
+            DEPTH.with(|depth| depth.set(depth.get() - 1));
+        }
+    }
+
+    let depth = DEPTH.with(Cell::get);
+    if depth >= RECURSION_LIMIT {
+        return Err(AprotoError::RecursionLimitExceeded);
+    }
+    DEPTH.with(|cell| cell.set(depth + 1));
+    let _level = Level;
+    decode()
+}
+
+/// Skips over the value of a field that the message does not know about.
+///
+/// A group is skipped up to and including the `EndGroup` key with the same tag.
+pub fn skip_field(wire_type: WireType, tag: u32, buf: &mut impl Buf) -> Result<(), AprotoError> {
+    let len = match wire_type {
+        WireType::Varint => {
+            decode_varint(buf)?;
+            0
+        }
+        WireType::Fixed64 => 8,
+        WireType::LengthDelimited => decode_length_delimiter(buf)?,
+        WireType::StartGroup => decode_nested(|| loop {
+            let (inner_tag, inner_wire_type) = decode_key(buf)?;
+            if inner_wire_type == WireType::EndGroup {
+                if inner_tag != tag {
+                    return Err(AprotoError::UnexpectedEndGroup(inner_tag));
+                }
+                break Ok(0);
+            }
+            skip_field(inner_wire_type, inner_tag, buf)?;
+        })?,
+        WireType::EndGroup => return Err(AprotoError::UnexpectedEndGroup(tag)),
+        WireType::Fixed32 => 4,
+    };
+
+    if len > buf.remaining() {
+        return Err(AprotoError::BufferUnderflow);
+    }
+    buf.advance(len);
+    Ok(())
+}
+
 #[allow(unused)]
 macro_rules! varint {
     ($ty:ty, $proto_ty:ident) => (
@@ -44,18 +179,1122 @@ macro_rules! varint {
                 encode_varint($to_uint64, buf);
             }
 
+            #[allow(unused)]
+            pub fn decode(wire_type: WireType, value: &mut $ty, buf: &mut impl Buf) -> Result<(), AprotoError> {
+                check_wire_type(WireType::Varint, wire_type)?;
+                let $from_uint64_value = decode_varint(buf)?;
+                *value = $from_uint64;
+                Ok(())
+            }
+
             #[allow(unused)]
             pub fn encode_len(tag: u32, $to_uint64_value: &$ty) -> usize {
                 tag_len(tag) + encoded_len($to_uint64)
             }
 
+            #[allow(unused)]
+            pub fn encode_repeated(tag: u32, values: &[$ty], buf: &mut impl BufMut) {
+                for value in values {
+                    encode(tag, value, buf);
+                }
+            }
+
+            #[allow(unused)]
+            pub fn encode_packed(tag: u32, values: &[$ty], buf: &mut impl BufMut) {
+                if values.is_empty() {
+                    return;
+                }
+
+                encode_tag(tag, WireType::LengthDelimited, buf);
+                let len: usize = values.iter().map(|$to_uint64_value| encoded_len($to_uint64)).sum();
+                encode_varint(len as u64, buf);
+                for $to_uint64_value in values {
+                    encode_varint($to_uint64, buf);
+                }
+            }
+
+            #[allow(unused)]
+            pub fn decode_repeated(wire_type: WireType, values: &mut Vec<$ty>, buf: &mut impl Buf) -> Result<(), AprotoError> {
+                decode_packable(wire_type, WireType::Varint, values, buf, decode)
+            }
+
+            #[allow(unused)]
+            pub fn encode_len_repeated(tag: u32, values: &[$ty]) -> usize {
+                values.iter().map(|value| encode_len(tag, value)).sum()
+            }
+
+            #[allow(unused)]
+            pub fn encode_len_packed(tag: u32, values: &[$ty]) -> usize {
+                if values.is_empty() {
+                    return 0;
+                }
+
+                let len: usize = values.iter().map(|$to_uint64_value| encoded_len($to_uint64)).sum();
+                tag_len(tag) + encoded_len(len as u64) + len
+            }
+
         }
     );
 }
 
 varint!(u64, uint64);
 varint!(u32, uint32);
+varint!(i64, int64);
 varint!(i32, int32);
+varint!(
+    i32,
+    sint32,
+    to_uint64(value) {
+        ((*value << 1) ^ (*value >> 31)) as u32 as u64
+    },
+    from_uint64(value) {
+        let value = value as u32;
+        ((value >> 1) as i32) ^ (-((value & 1) as i32))
+    }
+);
+varint!(
+    i64,
+    sint64,
+    to_uint64(value) {
+        ((*value << 1) ^ (*value >> 63)) as u64
+    },
+    from_uint64(value) {
+        ((value >> 1) as i64) ^ (-((value & 1) as i64))
+    }
+);
 varint!(bool, bool,
         to_uint64(value) u64::from(*value),
         from_uint64(value) value != 0);
+
+#[allow(unused)]
+macro_rules! fixed_width {
+    ($ty:ty,
+     $width:expr,
+     $wire_type:expr,
+     $proto_ty:ident,
+     $put:ident,
+     $get:ident) => {
+        pub mod $proto_ty {
+
+            use crate::encoding::*;
+
+            #[allow(unused)]
+            pub fn encode(tag: u32, value: &$ty, buf: &mut impl BufMut) {
+                encode_tag(tag, $wire_type, buf);
+                buf.$put(*value);
+            }
+
+            #[allow(unused)]
+            pub fn decode(
+                wire_type: WireType,
+                value: &mut $ty,
+                buf: &mut impl Buf,
+            ) -> Result<(), AprotoError> {
+                check_wire_type($wire_type, wire_type)?;
+                if buf.remaining() < $width {
+                    return Err(AprotoError::BufferUnderflow);
+                }
+                *value = buf.$get();
+                Ok(())
+            }
+
+            #[allow(unused)]
+            pub fn encode_len(tag: u32, _: &$ty) -> usize {
+                tag_len(tag) + $width
+            }
+
+            #[allow(unused)]
+            pub fn encode_repeated(tag: u32, values: &[$ty], buf: &mut impl BufMut) {
+                for value in values {
+                    encode(tag, value, buf);
+                }
+            }
+
+            #[allow(unused)]
+            pub fn encode_packed(tag: u32, values: &[$ty], buf: &mut impl BufMut) {
+                if values.is_empty() {
+                    return;
+                }
+
+                encode_tag(tag, WireType::LengthDelimited, buf);
+                encode_varint((values.len() * $width) as u64, buf);
+                for value in values {
+                    buf.$put(*value);
+                }
+            }
+
+            #[allow(unused)]
+            pub fn decode_repeated(
+                wire_type: WireType,
+                values: &mut Vec<$ty>,
+                buf: &mut impl Buf,
+            ) -> Result<(), AprotoError> {
+                decode_packable(wire_type, $wire_type, values, buf, decode)
+            }
+
+            #[allow(unused)]
+            pub fn encode_len_repeated(tag: u32, values: &[$ty]) -> usize {
+                (tag_len(tag) + $width) * values.len()
+            }
+
+            #[allow(unused)]
+            pub fn encode_len_packed(tag: u32, values: &[$ty]) -> usize {
+                if values.is_empty() {
+                    return 0;
+                }
+
+                let len = values.len() * $width;
+                tag_len(tag) + encoded_len(len as u64) + len
+            }
+        }
+    };
+}
+
+fixed_width!(u32, 4, WireType::Fixed32, fixed32, put_u32_le, get_u32_le);
+fixed_width!(u64, 8, WireType::Fixed64, fixed64, put_u64_le, get_u64_le);
+fixed_width!(i32, 4, WireType::Fixed32, sfixed32, put_i32_le, get_i32_le);
+fixed_width!(i64, 8, WireType::Fixed64, sfixed64, put_i64_le, get_i64_le);
+fixed_width!(f32, 4, WireType::Fixed32, float, put_f32_le, get_f32_le);
+fixed_width!(f64, 8, WireType::Fixed64, double, put_f64_le, get_f64_le);
+
+/// Storage for a `bytes` field, either `Vec<u8>` or `bytes::Bytes`.
+pub trait BytesAdapter: Default {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Replaces the contents with the first `len` bytes of `buf`.
+    fn replace_with(&mut self, buf: &mut impl Buf, len: usize);
+
+    fn append_to(&self, buf: &mut impl BufMut);
+}
+
+impl BytesAdapter for Vec<u8> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn replace_with(&mut self, buf: &mut impl Buf, len: usize) {
+        self.clear();
+        self.reserve(len);
+        self.put(buf.take(len));
+    }
+
+    fn append_to(&self, buf: &mut impl BufMut) {
+        buf.put_slice(self);
+    }
+}
+
+impl BytesAdapter for Bytes {
+    fn len(&self) -> usize {
+        Bytes::len(self)
+    }
+
+    fn replace_with(&mut self, buf: &mut impl Buf, len: usize) {
+        // Zero-copy when decoding from a `Bytes` buffer
+        *self = buf.copy_to_bytes(len);
+    }
+
+    fn append_to(&self, buf: &mut impl BufMut) {
+        buf.put_slice(self);
+    }
+}
+
+pub mod bytes {
+
+    use crate::encoding::*;
+
+    #[allow(unused)]
+    pub fn encode(tag: u32, value: &impl BytesAdapter, buf: &mut impl BufMut) {
+        encode_tag(tag, WireType::LengthDelimited, buf);
+        encode_varint(value.len() as u64, buf);
+        value.append_to(buf);
+    }
+
+    #[allow(unused)]
+    pub fn decode(
+        wire_type: WireType,
+        value: &mut impl BytesAdapter,
+        buf: &mut impl Buf,
+    ) -> Result<(), AprotoError> {
+        check_wire_type(WireType::LengthDelimited, wire_type)?;
+        let len = decode_length_delimiter(buf)?;
+        value.replace_with(buf, len);
+        Ok(())
+    }
+
+    #[allow(unused)]
+    pub fn encode_len(tag: u32, value: &impl BytesAdapter) -> usize {
+        tag_len(tag) + encoded_len(value.len() as u64) + value.len()
+    }
+
+    #[allow(unused)]
+    pub fn encode_repeated(tag: u32, values: &[impl BytesAdapter], buf: &mut impl BufMut) {
+        for value in values {
+            encode(tag, value, buf);
+        }
+    }
+
+    #[allow(unused)]
+    pub fn decode_repeated<B: BytesAdapter>(
+        wire_type: WireType,
+        values: &mut Vec<B>,
+        buf: &mut impl Buf,
+    ) -> Result<(), AprotoError> {
+        let mut value = B::default();
+        decode(wire_type, &mut value, buf)?;
+        values.push(value);
+        Ok(())
+    }
+
+    #[allow(unused)]
+    pub fn encode_len_repeated(tag: u32, values: &[impl BytesAdapter]) -> usize {
+        values.iter().map(|value| encode_len(tag, value)).sum()
+    }
+}
+
+pub mod string {
+
+    use crate::encoding::*;
+
+    #[allow(unused)]
+    pub fn encode(tag: u32, value: &str, buf: &mut impl BufMut) {
+        encode_tag(tag, WireType::LengthDelimited, buf);
+        encode_varint(value.len() as u64, buf);
+        buf.put_slice(value.as_bytes());
+    }
+
+    /// Decodes a string, leaving `value` empty if it is not valid UTF-8.
+    #[allow(unused)]
+    pub fn decode(
+        wire_type: WireType,
+        value: &mut String,
+        buf: &mut impl Buf,
+    ) -> Result<(), AprotoError> {
+        let mut bytes = std::mem::take(value).into_bytes();
+        super::bytes::decode(wire_type, &mut bytes, buf)?;
+        *value = String::from_utf8(bytes).map_err(|_| AprotoError::InvalidUtf8)?;
+        Ok(())
+    }
+
+    #[allow(unused)]
+    pub fn encode_len(tag: u32, value: &str) -> usize {
+        tag_len(tag) + encoded_len(value.len() as u64) + value.len()
+    }
+
+    #[allow(unused)]
+    pub fn encode_repeated(tag: u32, values: &[String], buf: &mut impl BufMut) {
+        for value in values {
+            encode(tag, value, buf);
+        }
+    }
+
+    #[allow(unused)]
+    pub fn decode_repeated(
+        wire_type: WireType,
+        values: &mut Vec<String>,
+        buf: &mut impl Buf,
+    ) -> Result<(), AprotoError> {
+        let mut value = String::new();
+        decode(wire_type, &mut value, buf)?;
+        values.push(value);
+        Ok(())
+    }
+
+    #[allow(unused)]
+    pub fn encode_len_repeated(tag: u32, values: &[String]) -> usize {
+        values.iter().map(|value| encode_len(tag, value)).sum()
+    }
+}
+
+pub mod message {
+
+    use crate::{encoding::*, Message};
+
+    #[allow(unused)]
+    pub fn encode(tag: u32, value: &impl Message, buf: &mut impl BufMut) {
+        encode_tag(tag, WireType::LengthDelimited, buf);
+        encode_varint(value.encoded_len() as u64, buf);
+        value.encode_raw(buf);
+    }
+
+    /// Merges a length-delimited message into `value`.
+    #[allow(unused)]
+    pub fn decode(
+        wire_type: WireType,
+        value: &mut impl Message,
+        buf: &mut impl Buf,
+    ) -> Result<(), AprotoError> {
+        check_wire_type(WireType::LengthDelimited, wire_type)?;
+        let len = decode_length_delimiter(buf)?;
+        let end = buf.remaining() - len;
+        decode_nested(|| {
+            while buf.remaining() > end {
+                let (tag, wire_type) = decode_key(buf)?;
+                value.merge_field(tag, wire_type, buf)?;
+            }
+            Ok(())
+        })?;
+
+        if buf.remaining() != end {
+            return Err(AprotoError::DelimitedLengthExceeded);
+        }
+        Ok(())
+    }
+
+    #[allow(unused)]
+    pub fn encode_len(tag: u32, value: &impl Message) -> usize {
+        let len = value.encoded_len();
+        tag_len(tag) + encoded_len(len as u64) + len
+    }
+
+    #[allow(unused)]
+    pub fn encode_repeated(tag: u32, values: &[impl Message], buf: &mut impl BufMut) {
+        for value in values {
+            encode(tag, value, buf);
+        }
+    }
+
+    #[allow(unused)]
+    pub fn decode_repeated<M: Message>(
+        wire_type: WireType,
+        values: &mut Vec<M>,
+        buf: &mut impl Buf,
+    ) -> Result<(), AprotoError> {
+        let mut value = M::default();
+        decode(wire_type, &mut value, buf)?;
+        values.push(value);
+        Ok(())
+    }
+
+    #[allow(unused)]
+    pub fn encode_len_repeated(tag: u32, values: &[impl Message]) -> usize {
+        values.iter().map(|value| encode_len(tag, value)).sum()
+    }
+}
+
+/// Proto2 groups are encoded like messages, but between a `StartGroup` and an
+/// `EndGroup` key with the field's tag instead of with a length prefix.
+pub mod group {
+
+    use crate::{encoding::*, Message};
+
+    #[allow(unused)]
+    pub fn encode(tag: u32, value: &impl Message, buf: &mut impl BufMut) {
+        encode_tag(tag, WireType::StartGroup, buf);
+        value.encode_raw(buf);
+        encode_tag(tag, WireType::EndGroup, buf);
+    }
+
+    /// Merges the fields of a group into `value`, up to and including the
+    /// `EndGroup` key with the same tag.
+    #[allow(unused)]
+    pub fn decode(
+        tag: u32,
+        wire_type: WireType,
+        value: &mut impl Message,
+        buf: &mut impl Buf,
+    ) -> Result<(), AprotoError> {
+        check_wire_type(WireType::StartGroup, wire_type)?;
+        decode_nested(|| loop {
+            if !buf.has_remaining() {
+                return Err(AprotoError::BufferUnderflow);
+            }
+            match decode_key(buf)? {
+                (inner_tag, WireType::EndGroup) if inner_tag == tag => return Ok(()),
+                (inner_tag, WireType::EndGroup) => {
+                    return Err(AprotoError::UnexpectedEndGroup(inner_tag))
+                }
+                (inner_tag, wire_type) => value.merge_field(inner_tag, wire_type, buf)?,
+            }
+        })
+    }
+
+    #[allow(unused)]
+    pub fn encode_len(tag: u32, value: &impl Message) -> usize {
+        2 * tag_len(tag) + value.encoded_len()
+    }
+
+    #[allow(unused)]
+    pub fn encode_repeated(tag: u32, values: &[impl Message], buf: &mut impl BufMut) {
+        for value in values {
+            encode(tag, value, buf);
+        }
+    }
+
+    #[allow(unused)]
+    pub fn decode_repeated<M: Message>(
+        tag: u32,
+        wire_type: WireType,
+        values: &mut Vec<M>,
+        buf: &mut impl Buf,
+    ) -> Result<(), AprotoError> {
+        let mut value = M::default();
+        decode(tag, wire_type, &mut value, buf)?;
+        values.push(value);
+        Ok(())
+    }
+
+    #[allow(unused)]
+    pub fn encode_len_repeated(tag: u32, values: &[impl Message]) -> usize {
+        values.iter().map(|value| encode_len(tag, value)).sum()
+    }
+}
+
+/// Map fields are encoded as a repeated entry message per key, holding the key
+/// as field 1 and the value as field 2.
+pub mod map {
+
+    use std::{collections::HashMap, hash::Hash};
+
+    use crate::encoding::*;
+
+    const KEY_TAG: u32 = 1;
+    const VALUE_TAG: u32 = 2;
+
+    #[allow(unused)]
+    pub fn encode<K, V, B: BufMut>(
+        tag: u32,
+        values: &HashMap<K, V>,
+        buf: &mut B,
+        key_encode: impl Fn(u32, &K, &mut B),
+        key_encode_len: impl Fn(u32, &K) -> usize,
+        value_encode: impl Fn(u32, &V, &mut B),
+        value_encode_len: impl Fn(u32, &V) -> usize,
+    ) {
+        for (key, value) in values {
+            let len = key_encode_len(KEY_TAG, key) + value_encode_len(VALUE_TAG, value);
+            encode_tag(tag, WireType::LengthDelimited, buf);
+            encode_varint(len as u64, buf);
+            key_encode(KEY_TAG, key, buf);
+            value_encode(VALUE_TAG, value, buf);
+        }
+    }
+
+    /// Decodes a single map entry. A key or value missing from the entry
+    /// takes its type's default.
+    #[allow(unused)]
+    pub fn decode<K: Default + Eq + Hash, V: Default, B: Buf>(
+        wire_type: WireType,
+        values: &mut HashMap<K, V>,
+        buf: &mut B,
+        key_decode: impl Fn(WireType, &mut K, &mut B) -> Result<(), AprotoError>,
+        value_decode: impl Fn(WireType, &mut V, &mut B) -> Result<(), AprotoError>,
+    ) -> Result<(), AprotoError> {
+        check_wire_type(WireType::LengthDelimited, wire_type)?;
+        let len = decode_length_delimiter(buf)?;
+        let end = buf.remaining() - len;
+
+        let mut key = K::default();
+        let mut value = V::default();
+        while buf.remaining() > end {
+            match decode_key(buf)? {
+                (KEY_TAG, wire_type) => key_decode(wire_type, &mut key, buf)?,
+                (VALUE_TAG, wire_type) => value_decode(wire_type, &mut value, buf)?,
+                (tag, wire_type) => skip_field(wire_type, tag, buf)?,
+            }
+        }
+
+        if buf.remaining() != end {
+            return Err(AprotoError::DelimitedLengthExceeded);
+        }
+        values.insert(key, value);
+        Ok(())
+    }
+
+    #[allow(unused)]
+    pub fn encode_len<K, V>(
+        tag: u32,
+        values: &HashMap<K, V>,
+        key_encode_len: impl Fn(u32, &K) -> usize,
+        value_encode_len: impl Fn(u32, &V) -> usize,
+    ) -> usize {
+        values
+            .iter()
+            .map(|(key, value)| {
+                let len = key_encode_len(KEY_TAG, key) + value_encode_len(VALUE_TAG, value);
+                tag_len(tag) + encoded_len(len as u64) + len
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag() {
+        fn check(tag: u32, wire_type: WireType, encoded: &[u8]) {
+            let mut buf = Vec::new();
+            encode_tag(tag, wire_type, &mut buf);
+            assert_eq!(buf, encoded);
+            assert_eq!(tag_len(tag), encoded.len());
+        }
+
+        // Keys as written by protoc.
+        check(1, WireType::Varint, &[0x08]);
+        check(1, WireType::Fixed64, &[0x09]);
+        check(2, WireType::LengthDelimited, &[0x12]);
+        check(3, WireType::StartGroup, &[0x1B]);
+        check(3, WireType::EndGroup, &[0x1C]);
+        check(4, WireType::Fixed32, &[0x25]);
+        check(15, WireType::Varint, &[0x78]);
+        check(16, WireType::Varint, &[0x80, 0x01]);
+        check(2047, WireType::Fixed32, &[0xFD, 0x7F]);
+        check(2048, WireType::Varint, &[0x80, 0x80, 0x01]);
+        check(MAX_TAG, WireType::Varint, &[0xF8, 0xFF, 0xFF, 0xFF, 0x0F]);
+    }
+
+    #[test]
+    fn key() {
+        fn check(encoded: &[u8], tag: u32, wire_type: WireType) {
+            let mut buf = encoded;
+            assert_eq!(decode_key(&mut buf).unwrap(), (tag, wire_type));
+            assert!(buf.is_empty());
+        }
+
+        check(&[0x08], 1, WireType::Varint);
+        check(&[0x12], 2, WireType::LengthDelimited);
+        check(&[0x1B], 3, WireType::StartGroup);
+        check(&[0x1C], 3, WireType::EndGroup);
+        check(&[0x25], 4, WireType::Fixed32);
+        check(&[0x81, 0x01], 16, WireType::Fixed64);
+        check(&[0xF8, 0xFF, 0xFF, 0xFF, 0x0F], MAX_TAG, WireType::Varint);
+    }
+
+    #[test]
+    fn invalid_key() {
+        fn check(encoded: &[u8], expected: impl Fn(AprotoError) -> bool) {
+            let mut buf = encoded;
+            assert!(expected(decode_key(&mut buf).unwrap_err()));
+        }
+
+        check(&[0x00], |e| matches!(e, AprotoError::InvalidTag(0)));
+        check(&[0x02], |e| matches!(e, AprotoError::InvalidTag(0)));
+        check(
+            &[0x80, 0x80, 0x80, 0x80, 0x10],
+            |e| matches!(e, AprotoError::InvalidTag(t) if t == u64::from(MAX_TAG) + 1),
+        );
+        check(&[0xF8, 0xFF, 0xFF, 0xFF, 0xFF, 0x01], |e| {
+            matches!(e, AprotoError::InvalidTag(0x1_FFFF_FFFF))
+        });
+        check(&[0x0E], |e| matches!(e, AprotoError::InvalidWireType(6)));
+        check(&[0x0F], |e| matches!(e, AprotoError::InvalidWireType(7)));
+        check(&[0x88], |e| matches!(e, AprotoError::VarintTruncated(1)));
+    }
+
+    #[test]
+    fn zigzag() {
+        fn check_sint32(value: i32, zigzag: u64) {
+            let mut buf = Vec::new();
+            sint32::encode(1, &value, &mut buf);
+            let mut expected = vec![0x08];
+            encode_varint(zigzag, &mut expected);
+            assert_eq!(buf, expected);
+            assert_eq!(sint32::encode_len(1, &value), expected.len());
+
+            let mut decoded = 0;
+            sint32::decode(WireType::Varint, &mut decoded, &mut &buf[1..]).unwrap();
+            assert_eq!(decoded, value);
+        }
+
+        fn check_sint64(value: i64, zigzag: u64) {
+            let mut buf = Vec::new();
+            sint64::encode(1, &value, &mut buf);
+            let mut expected = vec![0x08];
+            encode_varint(zigzag, &mut expected);
+            assert_eq!(buf, expected);
+            assert_eq!(sint64::encode_len(1, &value), expected.len());
+
+            let mut decoded = 0;
+            sint64::decode(WireType::Varint, &mut decoded, &mut &buf[1..]).unwrap();
+            assert_eq!(decoded, value);
+        }
+
+        check_sint32(0, 0);
+        check_sint32(-1, 1);
+        check_sint32(1, 2);
+        check_sint32(-2, 3);
+        check_sint32(i32::MAX, 0xFFFF_FFFE);
+        check_sint32(i32::MIN, 0xFFFF_FFFF);
+
+        check_sint64(0, 0);
+        check_sint64(-1, 1);
+        check_sint64(1, 2);
+        check_sint64(-2, 3);
+        check_sint64(i64::MAX, 0xFFFF_FFFF_FFFF_FFFE);
+        check_sint64(i64::MIN, u64::MAX);
+    }
+
+    #[test]
+    fn fixed_width() {
+        let mut buf = Vec::new();
+        fixed32::encode(1, &0x0102_0304, &mut buf);
+        assert_eq!(buf, [0x0D, 0x04, 0x03, 0x02, 0x01]);
+        assert_eq!(fixed32::encode_len(1, &0), buf.len());
+
+        let mut buf = Vec::new();
+        sfixed64::encode(2, &-2, &mut buf);
+        assert_eq!(buf, [0x11, 0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(sfixed64::encode_len(2, &0), buf.len());
+
+        let mut value = 0;
+        sfixed64::decode(WireType::Fixed64, &mut value, &mut &buf[1..]).unwrap();
+        assert_eq!(value, -2);
+    }
+
+    #[test]
+    fn fixed_width_errors() {
+        let mut value = 0u32;
+        assert!(matches!(
+            fixed32::decode(WireType::Fixed32, &mut value, &mut &[0x01, 0x02, 0x03][..]),
+            Err(AprotoError::BufferUnderflow)
+        ));
+        assert!(matches!(
+            fixed32::decode(WireType::Fixed64, &mut value, &mut &[0x01; 8][..]),
+            Err(AprotoError::UnexpectedWireType {
+                expected: 5,
+                actual: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn float_double() {
+        let mut buf = Vec::new();
+        float::encode(1, &1.5, &mut buf);
+        assert_eq!(buf, [0x0D, 0x00, 0x00, 0xC0, 0x3F]);
+        assert_eq!(float::encode_len(1, &0.0), buf.len());
+
+        let mut buf = Vec::new();
+        double::encode(2, &-2.0, &mut buf);
+        assert_eq!(buf, [0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0]);
+        assert_eq!(double::encode_len(2, &0.0), buf.len());
+    }
+
+    #[test]
+    fn float_double_bit_exact() {
+        for bits in [0x8000_0000, 0x7FC0_0001, 0xFFBF_FFFF, 0x7F80_0001] {
+            let mut buf = Vec::new();
+            float::encode(1, &f32::from_bits(bits), &mut buf);
+            let mut value = 0.0;
+            float::decode(WireType::Fixed32, &mut value, &mut &buf[1..]).unwrap();
+            assert_eq!(value.to_bits(), bits);
+        }
+
+        for bits in [
+            0x8000_0000_0000_0000,
+            0x7FF8_0000_0000_0001,
+            0xFFF7_FFFF_FFFF_FFFF,
+            0x7FF0_0000_0000_0001,
+        ] {
+            let mut buf = Vec::new();
+            double::encode(1, &f64::from_bits(bits), &mut buf);
+            let mut value = 0.0;
+            double::decode(WireType::Fixed64, &mut value, &mut &buf[1..]).unwrap();
+            assert_eq!(value.to_bits(), bits);
+        }
+    }
+
+    #[test]
+    fn string() {
+        let mut buf = Vec::new();
+        let value = "testing".to_string();
+        string::encode(2, &value, &mut buf);
+        assert_eq!(buf, b"\x12\x07testing");
+        assert_eq!(string::encode_len(2, &value), buf.len());
+
+        let mut decoded = "previous".to_string();
+        string::decode(WireType::LengthDelimited, &mut decoded, &mut &buf[1..]).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn string_invalid_utf8() {
+        let mut decoded = String::new();
+        assert!(matches!(
+            string::decode(
+                WireType::LengthDelimited,
+                &mut decoded,
+                &mut &[0x02, 0xC3, 0x28][..]
+            ),
+            Err(AprotoError::InvalidUtf8)
+        ));
+        assert!(decoded.is_empty());
+    }
+
+    #[test]
+    fn bytes() {
+        fn check<B: BytesAdapter + std::fmt::Debug + PartialEq>(value: B) {
+            let mut buf = Vec::new();
+            bytes::encode(1, &value, &mut buf);
+            assert_eq!(buf, [0x0A, 0x03, 0x01, 0x02, 0x03]);
+            assert_eq!(bytes::encode_len(1, &value), buf.len());
+
+            let mut decoded = B::default();
+            let mut input = Bytes::from(buf).slice(1..);
+            bytes::decode(WireType::LengthDelimited, &mut decoded, &mut input).unwrap();
+            assert_eq!(decoded, value);
+            assert!(input.is_empty());
+        }
+
+        check(vec![1u8, 2, 3]);
+        check(Bytes::from_static(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn bytes_truncated() {
+        let mut decoded = Vec::new();
+        assert!(matches!(
+            bytes::decode(
+                WireType::LengthDelimited,
+                &mut decoded,
+                &mut &[0x03, 0x01][..]
+            ),
+            Err(AprotoError::BufferUnderflow)
+        ));
+    }
+
+    #[test]
+    fn packed() {
+        let values = [3, 270, 86942];
+        let mut buf = Vec::new();
+        int32::encode_packed(4, &values, &mut buf);
+        // Example from the protobuf encoding guide.
+        assert_eq!(buf, [0x22, 0x06, 0x03, 0x8E, 0x02, 0x9E, 0xA7, 0x05]);
+        assert_eq!(int32::encode_len_packed(4, &values), buf.len());
+
+        let mut decoded = Vec::new();
+        int32::decode_repeated(WireType::LengthDelimited, &mut decoded, &mut &buf[1..]).unwrap();
+        assert_eq!(decoded, values);
+
+        let mut buf = Vec::new();
+        fixed32::encode_packed(1, &[1, 2], &mut buf);
+        assert_eq!(
+            buf,
+            [0x0A, 0x08, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00]
+        );
+        assert_eq!(fixed32::encode_len_packed(1, &[1, 2]), buf.len());
+
+        assert_eq!(int32::encode_len_packed(1, &[]), 0);
+        let mut buf = Vec::new();
+        int32::encode_packed(1, &[], &mut buf);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn unpacked() {
+        let values = [1u64, 300];
+        let mut buf = Vec::new();
+        uint64::encode_repeated(1, &values, &mut buf);
+        assert_eq!(buf, [0x08, 0x01, 0x08, 0xAC, 0x02]);
+        assert_eq!(uint64::encode_len_repeated(1, &values), buf.len());
+
+        let mut decoded = Vec::new();
+        let mut input = &buf[..];
+        while !input.is_empty() {
+            let (tag, wire_type) = decode_key(&mut input).unwrap();
+            assert_eq!(tag, 1);
+            uint64::decode_repeated(wire_type, &mut decoded, &mut input).unwrap();
+        }
+        assert_eq!(decoded, values);
+    }
+
+    #[test]
+    fn packed_length_mismatch() {
+        // The second varint runs past the packed length.
+        let mut decoded = Vec::new();
+        assert!(matches!(
+            uint32::decode_repeated(
+                WireType::LengthDelimited,
+                &mut decoded,
+                &mut &[0x02, 0x01, 0x80, 0x01][..]
+            ),
+            Err(AprotoError::DelimitedLengthExceeded)
+        ));
+
+        let mut decoded = Vec::new();
+        assert!(matches!(
+            fixed32::decode_repeated(
+                WireType::LengthDelimited,
+                &mut decoded,
+                &mut &[0x03, 0x01, 0x00, 0x00, 0x00][..]
+            ),
+            Err(AprotoError::DelimitedLengthExceeded)
+        ));
+    }
+
+    #[test]
+    fn map() {
+        use std::collections::HashMap;
+
+        let values = HashMap::from([("a".to_string(), 150u32)]);
+        let mut buf = Vec::new();
+        map::encode(
+            3,
+            &values,
+            &mut buf,
+            |tag, key, buf| string::encode(tag, key, buf),
+            |tag, key| string::encode_len(tag, key),
+            uint32::encode,
+            uint32::encode_len,
+        );
+        assert_eq!(buf, [0x1A, 0x06, 0x0A, 0x01, b'a', 0x10, 0x96, 0x01]);
+        assert_eq!(
+            map::encode_len(
+                3,
+                &values,
+                |tag, key| string::encode_len(tag, key),
+                uint32::encode_len
+            ),
+            buf.len()
+        );
+
+        let mut decoded = HashMap::new();
+        map::decode(
+            WireType::LengthDelimited,
+            &mut decoded,
+            &mut &buf[1..],
+            string::decode,
+            uint32::decode,
+        )
+        .unwrap();
+        assert_eq!(decoded, values);
+    }
+
+    #[test]
+    fn map_entry_defaults() {
+        use std::collections::HashMap;
+
+        // An empty entry, an entry with only a value and one with only a key.
+        let mut decoded = HashMap::new();
+        let mut buf: &[u8] = &[0x00, 0x02, 0x10, 0x07, 0x02, 0x08, 0x09];
+        for _ in 0..3 {
+            map::decode(
+                WireType::LengthDelimited,
+                &mut decoded,
+                &mut buf,
+                uint32::decode,
+                uint64::decode,
+            )
+            .unwrap();
+        }
+        assert_eq!(decoded, HashMap::from([(0, 7), (9, 0)]));
+    }
+
+    #[test]
+    fn message_exceeds_delimited_length() {
+        use crate::Message;
+
+        #[derive(Default)]
+        struct Unit(u64);
+
+        impl Message for Unit {
+            fn encode_raw(&self, buf: &mut impl BufMut) {
+                uint64::encode(1, &self.0, buf);
+            }
+
+            fn merge_field(
+                &mut self,
+                tag: u32,
+                wire_type: WireType,
+                buf: &mut impl Buf,
+            ) -> Result<(), AprotoError> {
+                match tag {
+                    1 => uint64::decode(wire_type, &mut self.0, buf),
+                    _ => skip_field(wire_type, tag, buf),
+                }
+            }
+
+            fn encoded_len(&self) -> usize {
+                uint64::encode_len(1, &self.0)
+            }
+
+            fn clear(&mut self) {
+                self.0 = 0;
+            }
+        }
+
+        // The length prefix covers the key but only part of the varint.
+        let mut unit = Unit::default();
+        assert!(matches!(
+            message::decode(
+                WireType::LengthDelimited,
+                &mut unit,
+                &mut &[0x02, 0x08, 0x96, 0x01][..]
+            ),
+            Err(AprotoError::DelimitedLengthExceeded)
+        ));
+    }
+
+    #[test]
+    fn group() {
+        use crate::Message;
+
+        #[derive(Default, Debug, PartialEq)]
+        struct Unit(u64);
+
+        impl Message for Unit {
+            fn encode_raw(&self, buf: &mut impl BufMut) {
+                uint64::encode(2, &self.0, buf);
+            }
+
+            fn merge_field(
+                &mut self,
+                tag: u32,
+                wire_type: WireType,
+                buf: &mut impl Buf,
+            ) -> Result<(), AprotoError> {
+                match tag {
+                    2 => uint64::decode(wire_type, &mut self.0, buf),
+                    _ => skip_field(wire_type, tag, buf),
+                }
+            }
+
+            fn encoded_len(&self) -> usize {
+                uint64::encode_len(2, &self.0)
+            }
+
+            fn clear(&mut self) {
+                self.0 = 0;
+            }
+        }
+
+        let mut buf = Vec::new();
+        group::encode(1, &Unit(150), &mut buf);
+        assert_eq!(buf, [0x0B, 0x10, 0x96, 0x01, 0x0C]);
+        assert_eq!(group::encode_len(1, &Unit(150)), buf.len());
+
+        // An unknown nested group inside the group is skipped.
+        let mut input: &[u8] = &[0x10, 0x96, 0x01, 0x1B, 0x1C, 0x0C, 0x08, 0x01];
+        let mut decoded = Unit::default();
+        group::decode(1, WireType::StartGroup, &mut decoded, &mut input).unwrap();
+        assert_eq!(decoded, Unit(150));
+        assert_eq!(input, &[0x08, 0x01]);
+
+        let mut decoded = Unit::default();
+        assert!(matches!(
+            group::decode(
+                1,
+                WireType::StartGroup,
+                &mut decoded,
+                &mut &[0x10, 0x01, 0x14][..]
+            ),
+            Err(AprotoError::UnexpectedEndGroup(2))
+        ));
+        assert!(matches!(
+            group::decode(
+                1,
+                WireType::StartGroup,
+                &mut decoded,
+                &mut &[0x10, 0x01][..]
+            ),
+            Err(AprotoError::BufferUnderflow)
+        ));
+        assert!(matches!(
+            group::decode(1, WireType::LengthDelimited, &mut decoded, &mut &[0x00][..]),
+            Err(AprotoError::UnexpectedWireType {
+                expected: 3,
+                actual: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn group_recursion_limit() {
+        use crate::Message;
+
+        #[derive(Default)]
+        struct Nest(Option<Box<Nest>>);
+
+        impl Message for Nest {
+            fn encode_raw(&self, buf: &mut impl BufMut) {
+                if let Some(inner) = &self.0 {
+                    group::encode(1, inner, buf);
+                }
+            }
+
+            fn merge_field(
+                &mut self,
+                tag: u32,
+                wire_type: WireType,
+                buf: &mut impl Buf,
+            ) -> Result<(), AprotoError> {
+                match tag {
+                    1 => group::decode(
+                        tag,
+                        wire_type,
+                        self.0.get_or_insert_with(Default::default),
+                        buf,
+                    ),
+                    _ => skip_field(wire_type, tag, buf),
+                }
+            }
+
+            fn encoded_len(&self) -> usize {
+                self.0
+                    .as_ref()
+                    .map_or(0, |inner| group::encode_len(1, inner))
+            }
+
+            fn clear(&mut self) {
+                self.0 = None;
+            }
+        }
+
+        // `depth` groups nested in each other.
+        let nested = |depth: usize| [[0x0B].repeat(depth), [0x0C].repeat(depth)].concat();
+
+        Nest::decode(&nested(RECURSION_LIMIT as usize)[..]).unwrap();
+        assert!(matches!(
+            Nest::decode(&nested(RECURSION_LIMIT as usize + 1)[..]),
+            Err(AprotoError::RecursionLimitExceeded)
+        ));
+    }
+
+    #[test]
+    fn skip_group() {
+        // Group 1 holding a varint field 2 and a nested group 3, followed by field 4.
+        let mut buf: &[u8] = &[0x10, 0x01, 0x1B, 0x1C, 0x0C, 0x20, 0x01];
+        skip_field(WireType::StartGroup, 1, &mut buf).unwrap();
+        assert_eq!(buf, &[0x20, 0x01]);
+    }
+
+    #[test]
+    fn skip_group_mismatched_end() {
+        let mut buf: &[u8] = &[0x10, 0x01, 0x14];
+        assert!(matches!(
+            skip_field(WireType::StartGroup, 1, &mut buf),
+            Err(AprotoError::UnexpectedEndGroup(2))
+        ));
+    }
+
+    #[test]
+    fn skip_nested_groups() {
+        // `depth` groups with tag 1 nested in each other.
+        let nested = |depth: usize| [[0x0B].repeat(depth), [0x0C].repeat(depth)].concat();
+
+        let buf = nested(RECURSION_LIMIT as usize);
+        skip_field(WireType::StartGroup, 1, &mut &buf[1..]).unwrap();
+
+        let buf = nested(RECURSION_LIMIT as usize + 1);
+        assert!(matches!(
+            skip_field(WireType::StartGroup, 1, &mut &buf[1..]),
+            Err(AprotoError::RecursionLimitExceeded)
+        ));
+        // The depth is reset after the error.
+        skip_field(WireType::StartGroup, 1, &mut &nested(2)[1..]).unwrap();
+    }
+
+    #[test]
+    fn skip_truncated_field() {
+        let mut buf: &[u8] = &[0x05, 0x01];
+        assert!(matches!(
+            skip_field(WireType::LengthDelimited, 1, &mut buf),
+            Err(AprotoError::BufferUnderflow)
+        ));
+    }
+}
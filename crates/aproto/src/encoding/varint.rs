@@ -1,6 +1,7 @@
 use std::num::NonZeroU64;
 
-use bytes::BufMut;
+use aproto_types::error::AprotoError;
+use bytes::{Buf, BufMut};
 
 #[allow(unused)]
 #[inline]
@@ -30,6 +31,31 @@ pub const fn encoded_len(value: u64) -> usize {
     ((log2value * 9 + (64 + 9)) / 64) as usize
 }
 
+/// Decodes a varint from the front of `buf`.
+///
+/// A varint is at most 10 bytes long. The 10th byte may only carry the single
+/// remaining bit of a `u64`, so anything above `0x01` there is an overflow.
+#[allow(unused)]
+#[inline]
+pub fn decode_varint(buf: &mut impl Buf) -> Result<u64, AprotoError> {
+    let mut value = 0;
+    for count in 0..10 {
+        if !buf.has_remaining() {
+            return Err(AprotoError::VarintTruncated(count));
+        }
+        let byte = buf.get_u8();
+        if count == 9 && byte > 0x01 {
+            return Err(AprotoError::VarintOverflow);
+        }
+        // Lower 7 bits carry the payload, least significant group first
+        value |= u64::from(byte & 0x7F) << (count * 7);
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    unreachable!("the 10th byte either ends the varint or overflows")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -48,6 +74,10 @@ mod tests {
             assert_eq!(buf, encoded);
 
             assert_eq!(encoded_len(value), encoded.len());
+
+            let mut encoded_copy = encoded;
+            assert_eq!(decode_varint(&mut encoded_copy).unwrap(), value);
+            assert!(encoded_copy.is_empty());
         }
 
         check(2u64.pow(0) - 1, &[0x00]);
@@ -104,4 +134,45 @@ mod tests {
             &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01],
         );
     }
+
+    #[test]
+    fn decode_varint_truncated() {
+        fn check(encoded: &[u8]) {
+            let mut buf = encoded;
+            match decode_varint(&mut buf) {
+                Err(AprotoError::VarintTruncated(count)) => assert_eq!(count, encoded.len()),
+                other => panic!("expected truncation error, got {:?}", other),
+            }
+        }
+
+        check(&[]);
+        check(&[0x80]);
+        check(&[0xFF, 0xFF]);
+        check(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn decode_varint_overflow() {
+        fn check(encoded: &[u8]) {
+            let mut buf = encoded;
+            assert!(matches!(
+                decode_varint(&mut buf),
+                Err(AprotoError::VarintOverflow)
+            ));
+        }
+
+        check(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x02]);
+        check(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F]);
+        // A continuation bit on the 10th byte would need an 11th byte.
+        check(&[
+            0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01,
+        ]);
+    }
+
+    #[test]
+    fn decode_varint_leaves_trailing_bytes() {
+        let mut buf: &[u8] = &[0xAC, 0x02, 0x01];
+        assert_eq!(decode_varint(&mut buf).unwrap(), 300);
+        assert_eq!(buf, &[0x01]);
+    }
 }
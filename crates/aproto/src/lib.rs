@@ -1,2 +1,10 @@
-mod encoding;
+#[doc(hidden)]
+pub mod encoding;
+mod message;
 mod wire_type;
+
+pub use aproto_macros::{include_proto, message, proto};
+pub use aproto_types::error::AprotoError;
+pub use bytes;
+pub use message::Message;
+pub use wire_type::WireType;
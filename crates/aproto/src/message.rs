@@ -0,0 +1,113 @@
+use aproto_types::error::AprotoError;
+use bytes::{Buf, BufMut};
+
+use crate::{encoding::decode_key, wire_type::WireType};
+
+/// A protobuf message.
+///
+/// Implemented by the structs generated with [`message!`](crate::message), so
+/// user code can encode and decode any generated type generically.
+pub trait Message: Default {
+    /// Encodes the fields of the message into `buf`, without checking that
+    /// the buffer has enough capacity.
+    ///
+    /// Called by the provided methods; prefer [`Message::encode`].
+    fn encode_raw(&self, buf: &mut impl BufMut);
+
+    /// Decodes a single field, whose key has already been read, into the
+    /// message. Unknown fields are skipped.
+    ///
+    /// Called by the provided methods; prefer [`Message::merge`].
+    fn merge_field(
+        &mut self,
+        tag: u32,
+        wire_type: WireType,
+        buf: &mut impl Buf,
+    ) -> Result<(), AprotoError>;
+
+    /// Returns the number of bytes the encoded message occupies.
+    fn encoded_len(&self) -> usize;
+
+    /// Resets every field of the message to its default value.
+    fn clear(&mut self);
+
+    /// Checks that every required field of the message, and of the messages
+    /// it holds, is set. Only proto2 messages have required fields.
+    fn check_initialized(&self) -> Result<(), AprotoError> {
+        Ok(())
+    }
+
+    /// Encodes the message into `buf`.
+    ///
+    /// Fails without writing anything if `buf` cannot hold the whole message.
+    fn encode(&self, buf: &mut impl BufMut) -> Result<(), AprotoError> {
+        let required = self.encoded_len();
+        let remaining = buf.remaining_mut();
+        if required > remaining {
+            return Err(AprotoError::InsufficientBuffer {
+                required,
+                remaining,
+            });
+        }
+
+        self.encode_raw(buf);
+        Ok(())
+    }
+
+    /// Encodes the message into a newly allocated vector.
+    fn encode_to_vec(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.encoded_len());
+        self.encode_raw(&mut buf);
+        buf
+    }
+
+    /// Decodes a message from `buf`.
+    ///
+    /// Fails with [`AprotoError::RecursionLimitExceeded`] if messages and
+    /// groups are nested more than 100 levels deep in `buf`.
+    fn decode(mut buf: impl Buf) -> Result<Self, AprotoError> {
+        let mut message = Self::default();
+        message.merge(&mut buf)?;
+        Ok(message)
+    }
+
+    /// Decodes fields from `buf` into the message. Fields present in `buf`
+    /// overwrite, or for repeated fields extend, the existing values.
+    ///
+    /// Fails if a required field is still unset once `buf` is decoded.
+    fn merge(&mut self, mut buf: impl Buf) -> Result<(), AprotoError> {
+        while buf.has_remaining() {
+            let (tag, wire_type) = decode_key(&mut buf)?;
+            self.merge_field(tag, wire_type, &mut buf)?;
+        }
+        self.check_initialized()
+    }
+}
+
+/// Boxed messages let a message type contain itself.
+impl<M: Message> Message for Box<M> {
+    fn encode_raw(&self, buf: &mut impl BufMut) {
+        (**self).encode_raw(buf)
+    }
+
+    fn merge_field(
+        &mut self,
+        tag: u32,
+        wire_type: WireType,
+        buf: &mut impl Buf,
+    ) -> Result<(), AprotoError> {
+        (**self).merge_field(tag, wire_type, buf)
+    }
+
+    fn encoded_len(&self) -> usize {
+        (**self).encoded_len()
+    }
+
+    fn clear(&mut self) {
+        (**self).clear()
+    }
+
+    fn check_initialized(&self) -> Result<(), AprotoError> {
+        (**self).check_initialized()
+    }
+}
@@ -7,7 +7,9 @@ pub enum WireType {
     Varint = 0,
     Fixed64 = 1,
     LengthDelimited = 2,
-    Fixed32 = 3,
+    StartGroup = 3,
+    EndGroup = 4,
+    Fixed32 = 5,
 }
 
 impl TryFrom<u64> for WireType {
@@ -18,8 +20,39 @@ impl TryFrom<u64> for WireType {
             0 => Ok(WireType::Varint),
             1 => Ok(WireType::Fixed64),
             2 => Ok(WireType::LengthDelimited),
-            3 => Ok(WireType::Fixed32),
+            3 => Ok(WireType::StartGroup),
+            4 => Ok(WireType::EndGroup),
+            5 => Ok(WireType::Fixed32),
             _ => Err(AprotoError::InvalidWireType(value)),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wire_type_roundtrip() {
+        for wire_type in [
+            WireType::Varint,
+            WireType::Fixed64,
+            WireType::LengthDelimited,
+            WireType::StartGroup,
+            WireType::EndGroup,
+            WireType::Fixed32,
+        ] {
+            assert_eq!(WireType::try_from(wire_type as u64).unwrap(), wire_type);
+        }
+    }
+
+    #[test]
+    fn invalid_wire_type() {
+        for value in [6, 7, 8, u64::MAX] {
+            assert!(matches!(
+                WireType::try_from(value),
+                Err(AprotoError::InvalidWireType(v)) if v == value
+            ));
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use aproto::Message;
+
+aproto::message! {
+    enum Color {
+        COLOR_UNSPECIFIED = 0;
+        RED = 1;
+        NEGATIVE = -1;
+    }
+}
+
+aproto::message! {
+    enum Relation {
+        NONE = 0;
+        SELF = 1;
+    }
+}
+
+aproto::message! {
+    message Task {
+        string title = 1;
+        Status status = 2;
+        repeated Status history = 3;
+        map<string, Status> by_owner = 4;
+
+        enum Status {
+            UNKNOWN = 0;
+            ACTIVE = 1;
+            DONE = 2;
+        }
+    }
+}
+
+#[test]
+fn test_top_level_enum() {
+    assert_eq!(Color::default(), Color::ColorUnspecified);
+    assert_eq!(i32::from(Color::Red), 1);
+    assert_eq!(Color::try_from(-1).unwrap(), Color::Negative);
+
+    let err = Color::try_from(7).unwrap_err();
+    assert_eq!(err.to_string(), "unknown Color value: 7");
+}
+
+#[test]
+fn test_keyword_variant() {
+    assert_eq!(Relation::try_from(1).unwrap(), Relation::Self_);
+}
+
+#[test]
+fn test_nested_enum() {
+    use task::Status;
+
+    assert_eq!(Status::default(), Status::Unknown);
+    assert_eq!(Status::try_from(2).unwrap(), Status::Done);
+    let value: i32 = Status::Active.into();
+    assert_eq!(value, 1);
+}
+
+#[test]
+fn test_enum_fields_encode_as_varints() {
+    use task::Status;
+
+    let task = Task {
+        title: String::new(),
+        status: Status::Done.into(),
+        history: vec![Status::Unknown.into(), Status::Active.into()],
+        by_owner: HashMap::from([("a".to_string(), Status::Active.into())]),
+    };
+    #[rustfmt::skip]
+    let expected = [
+        // title is empty, so not encoded
+        // status
+        0x10, 0x02,
+        // history, packed
+        0x1A, 0x02, 0x00, 0x01,
+        // by_owner entry
+        0x22, 0x05, 0x0A, 0x01, b'a', 0x10, 0x01,
+    ];
+    let encoded = task.encode_to_vec();
+    assert_eq!(encoded, expected);
+    assert_eq!(task.encoded_len(), encoded.len());
+    assert_eq!(Task::decode(&encoded[..]).unwrap(), task);
+}
+
+#[test]
+fn test_unknown_enum_value_is_preserved() {
+    let task = Task::decode(&[0x10, 0x09][..]).unwrap();
+    assert_eq!(task.status, 9);
+    assert!(task::Status::try_from(task.status).is_err());
+    assert_eq!(task.encode_to_vec(), [0x10, 0x09]);
+}
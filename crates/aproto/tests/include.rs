@@ -0,0 +1,77 @@
+use aproto::Message;
+
+aproto::include_proto!("tests/schemas/common.proto");
+aproto::include_proto!("tests/schemas/user.proto");
+
+mod blob {
+    aproto::include_proto!(#![aproto(bytes = "bytes")] "tests/schemas/blob.proto");
+}
+
+mod inline {
+    aproto::proto! {
+        syntax = "proto3";
+        package people;
+
+        message User {
+            uint64 id = 1;
+            string name = 2;
+            Role role = 3;
+            repeated Address addresses = 4;
+            map<string, people.Role> teams = 5;
+            optional string nickname = 6 [json_name = "nick/*name*/"];
+        }
+
+        enum Role {
+            ROLE_UNSPECIFIED = 0;
+            ROLE_ADMIN = 1;
+        }
+
+        message Address {
+            string street = 1;
+            string city = 2;
+        }
+    }
+}
+
+#[test]
+fn test_include_proto() {
+    let user = User {
+        id: 7,
+        name: "a".to_string(),
+        role: Role::RoleAdmin.into(),
+        addresses: vec![Address {
+            street: "b".to_string(),
+            city: "c".to_string(),
+        }],
+        teams: [("x".to_string(), Role::RoleAdmin.into())].into(),
+        nickname: Some(String::new()),
+    };
+    let inline = inline::User {
+        id: 7,
+        name: "a".to_string(),
+        role: inline::Role::RoleAdmin.into(),
+        addresses: vec![inline::Address {
+            street: "b".to_string(),
+            city: "c".to_string(),
+        }],
+        teams: [("x".to_string(), inline::Role::RoleAdmin.into())].into(),
+        nickname: Some(String::new()),
+    };
+
+    // The file on disk generates the same code as the same file inline
+    let encoded = user.encode_to_vec();
+    assert_eq!(encoded, inline.encode_to_vec());
+    assert_eq!(User::decode(&encoded[..]).unwrap(), user);
+    assert_eq!(user.nickname(), "");
+}
+
+#[test]
+fn test_include_proto_with_settings() {
+    let blob = blob::Blob {
+        data: aproto::bytes::Bytes::from_static(b"\x1f\x8b"),
+        compression: blob::Compression::CompressionGzip.into(),
+    };
+    let encoded = blob.encode_to_vec();
+    assert_eq!(encoded, [0x0a, 0x02, 0x1f, 0x8b, 0x10, 0x01]);
+    assert_eq!(blob::Blob::decode(&encoded[..]).unwrap(), blob);
+}
@@ -0,0 +1,717 @@
+use std::collections::HashMap;
+
+use aproto::Message;
+
+aproto::message! {
+    message Address {
+        string street = 1;
+        uint32 number = 2;
+    }
+}
+
+aproto::message! {
+    message Person {
+        string name = 1;
+        int32 age = 2;
+        repeated string hobbies = 3;
+        map<string, int32> scores = 4;
+        Address address = 5;
+        repeated Address previous_addresses = 6;
+        map<uint32, Address> by_id = 7;
+        bytes avatar = 8;
+    }
+}
+
+#[test]
+fn test_generated_struct_fields() {
+    let person = Person {
+        name: "Jane".to_string(),
+        age: 42,
+        hobbies: vec!["chess".to_string()],
+        scores: HashMap::from([("math".to_string(), 90)]),
+        address: Some(Address {
+            street: "Main".to_string(),
+            number: 1,
+        }),
+        previous_addresses: vec![Address::default()],
+        by_id: HashMap::from([(7, Address::default())]),
+        avatar: vec![0xFF],
+    };
+
+    assert_eq!(person.name, "Jane");
+    assert_eq!(person.address.unwrap().number, 1);
+    assert_eq!(person.by_id[&7], Address::default());
+}
+
+#[test]
+fn test_generated_struct_default() {
+    let person = Person::default();
+    assert!(person.name.is_empty());
+    assert_eq!(person.age, 0);
+    assert!(person.hobbies.is_empty());
+    assert!(person.scores.is_empty());
+    assert_eq!(person.address, None);
+}
+
+aproto::message! {
+    message Account {
+        uint64 id = 1;
+        int32 balance = 2;
+        int64 created_at = 3;
+        uint32 version = 4;
+        bool active = 5;
+    }
+}
+
+aproto::message! {
+    message Empty {}
+}
+
+aproto::message! {
+    message Delta {
+        sint32 small = 1;
+        sint64 large = 2;
+    }
+}
+
+aproto::message! {
+    message Checksum {
+        fixed32 crc = 1;
+        fixed64 hash = 2;
+        sfixed32 offset = 3;
+        sfixed64 delta = 4;
+    }
+}
+
+aproto::message! {
+    message Point {
+        float x = 1;
+        double y = 2;
+    }
+}
+
+aproto::message! {
+    message Profile {
+        string name = 1;
+        bytes avatar = 2;
+    }
+}
+
+aproto::message! {
+    #![aproto(bytes = "bytes")]
+    message Blob {
+        bytes data = 1;
+        string name = 2;
+        map<uint32, bytes> parts = 3;
+
+        message Chunk {
+            bytes data = 1;
+        }
+    }
+}
+
+aproto::message! {
+    message Series {
+        repeated int32 samples = 4;
+        repeated sint64 deltas = 5 [packed = false];
+        repeated double weights = 6;
+        repeated fixed32 ids = 7 [packed = false];
+        repeated string labels = 8;
+        repeated bytes blobs = 9;
+    }
+}
+
+aproto::message! {
+    message Directory {
+        map<string, uint32> ages = 1;
+        map<int64, Profile> profiles = 2;
+    }
+}
+
+aproto::message! {
+    message Team {
+        Profile lead = 1;
+        repeated Profile members = 2;
+        Point origin = 3;
+    }
+}
+
+aproto::message! {
+    message Node {
+        uint32 value = 1;
+        Node next = 2;
+    }
+}
+
+aproto::message! {
+    message Sparse {
+        int32 a = 1;
+        int32 b = 16;
+        bool c = 2048;
+        uint64 d = 536870911;
+    }
+}
+
+aproto::message! {
+    message Settings {
+        optional uint32 retries = 1;
+        optional string region = 2;
+        optional double ratio = 3;
+        optional bool verbose = 4;
+        uint32 timeout = 5;
+    }
+}
+
+aproto::message! {
+    message Keywords {
+        string type = 1;
+        optional int32 match = 2;
+        repeated string self = 3;
+        map<string, int32> loop = 4;
+        Sparse struct = 5;
+        oneof async {
+            bool move = 6;
+            string super = 7;
+        }
+    }
+}
+
+#[test]
+fn test_generated_account_fields() {
+    let account = Account {
+        id: 7,
+        balance: -20,
+        created_at: 1_700_000_000,
+        version: 3,
+        active: true,
+    };
+
+    assert_eq!(account.id, 7);
+    assert_eq!(account.balance, -20);
+    assert!(account.active);
+}
+
+#[test]
+fn test_generated_account_default() {
+    let account = Account::default();
+    assert_eq!(account.id, 0);
+    assert_eq!(account.balance, 0);
+    assert!(!account.active);
+}
+
+#[test]
+fn test_encoded_len_matches_encoding() {
+    let account = Account {
+        id: 300,
+        balance: -1,
+        created_at: 1,
+        version: 0,
+        active: true,
+    };
+
+    let mut buf = Vec::new();
+    account.encode(&mut buf).unwrap();
+    assert_eq!(buf.len(), account.encoded_len());
+    assert_eq!(account.encode_to_vec(), buf);
+}
+
+#[test]
+fn test_encode_insufficient_buffer() {
+    let account = Account {
+        id: 300,
+        ..Default::default()
+    };
+
+    let mut storage = [0u8; 2];
+    let mut buf = &mut storage[..];
+    let err = account.encode(&mut buf).unwrap_err();
+    assert!(matches!(
+        err,
+        aproto::AprotoError::InsufficientBuffer { remaining: 2, .. }
+    ));
+}
+
+#[test]
+fn test_decode() {
+    // id = 150, active = true, as produced by protoc.
+    let account = Account::decode(&[0x08, 0x96, 0x01, 0x28, 0x01][..]).unwrap();
+    assert_eq!(account.id, 150);
+    assert!(account.active);
+    assert_eq!(account.balance, 0);
+}
+
+#[test]
+fn test_decode_skips_unknown_fields() {
+    // Field 6 (varint), field 7 (length-delimited), field 8 (fixed64) and
+    // field 9 (fixed32) are unknown to `Account`.
+    let bytes = [
+        0x30, 0x05, 0x3A, 0x02, 0xAA, 0xBB, 0x41, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+        0x4D, 0x01, 0x02, 0x03, 0x04, 0x08, 0x01,
+    ];
+    let account = Account::decode(&bytes[..]).unwrap();
+    assert_eq!(account.id, 1);
+}
+
+#[test]
+fn test_merge_overwrites_fields() {
+    let mut account = Account {
+        id: 1,
+        balance: 5,
+        ..Default::default()
+    };
+    account.merge(&[0x08, 0x02][..]).unwrap();
+    assert_eq!(account.id, 2);
+    assert_eq!(account.balance, 5);
+}
+
+#[test]
+fn test_clear() {
+    let mut account = Account {
+        id: 1,
+        active: true,
+        ..Default::default()
+    };
+    account.clear();
+    assert_eq!(account, Account::default());
+}
+
+#[test]
+fn test_empty_message() {
+    let empty = Empty::default();
+    assert_eq!(empty.encoded_len(), 0);
+    assert!(empty.encode_to_vec().is_empty());
+    assert_eq!(Empty::decode(&[0x08, 0x01][..]).unwrap(), empty);
+}
+
+fn roundtrip_len<M: Message>(message: &M) -> usize {
+    message.encode_to_vec().len()
+}
+
+#[test]
+fn test_generic_over_message() {
+    assert_eq!(roundtrip_len(&Empty::default()), 0);
+    assert_eq!(
+        roundtrip_len(&Account::default()),
+        Account::default().encoded_len()
+    );
+}
+
+#[test]
+fn test_decode_truncated_varint() {
+    // `id` announces a second varint byte that never arrives.
+    let err = Account::decode(&[0x08, 0x96][..]).unwrap_err();
+    match err {
+        aproto::AprotoError::Field {
+            message,
+            field,
+            source,
+        } => {
+            assert_eq!((message, field), ("Account", "id"));
+            assert!(matches!(*source, aproto::AprotoError::VarintTruncated(1)));
+        }
+        other => panic!("expected field error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_golden_bytes() {
+    // Bytes produced by protoc for the same field values.
+    let account = Account {
+        id: 150,
+        balance: -1,
+        created_at: 1,
+        version: 2,
+        active: true,
+    };
+    assert_eq!(
+        account.encode_to_vec(),
+        [
+            0x08, 0x96, 0x01, 0x10, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01,
+            0x18, 0x01, 0x20, 0x02, 0x28, 0x01,
+        ]
+    );
+
+    let sparse = Sparse {
+        a: 1,
+        b: 2,
+        c: true,
+        d: 3,
+    };
+    let encoded = [
+        0x08, 0x01, 0x80, 0x01, 0x02, 0x80, 0x80, 0x01, 0x01, 0xF8, 0xFF, 0xFF, 0xFF, 0x0F, 0x03,
+    ];
+    assert_eq!(sparse.encode_to_vec(), encoded);
+    assert_eq!(sparse.encoded_len(), encoded.len());
+    assert_eq!(Sparse::decode(&encoded[..]).unwrap(), sparse);
+}
+
+#[test]
+fn test_roundtrip() {
+    let account = Account {
+        id: u64::MAX,
+        balance: i32::MIN,
+        created_at: i64::MIN,
+        version: u32::MAX,
+        active: true,
+    };
+    assert_eq!(
+        Account::decode(&account.encode_to_vec()[..]).unwrap(),
+        account
+    );
+}
+
+#[test]
+fn test_decode_skips_unknown_group() {
+    // Group 9 holding a varint field 1, then `id = 1`.
+    let bytes = [0x4B, 0x08, 0x01, 0x4C, 0x08, 0x01];
+    let account = Account::decode(&bytes[..]).unwrap();
+    assert_eq!(account.id, 1);
+}
+
+#[test]
+fn test_decode_rejects_field_number_zero() {
+    let err = Account::decode(&[0x00, 0x01][..]).unwrap_err();
+    assert!(matches!(err, aproto::AprotoError::InvalidTag(0)));
+}
+
+#[test]
+fn test_zigzag_fields() {
+    let delta = Delta {
+        small: -2,
+        large: i64::MIN,
+    };
+    let encoded = delta.encode_to_vec();
+    assert_eq!(
+        encoded,
+        [0x08, 0x03, 0x10, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]
+    );
+    assert_eq!(delta.encoded_len(), encoded.len());
+    assert_eq!(Delta::decode(&encoded[..]).unwrap(), delta);
+}
+
+#[test]
+fn test_fixed_width_fields() {
+    let checksum = Checksum {
+        crc: 1,
+        hash: u64::MAX,
+        offset: -1,
+        delta: 2,
+    };
+    let encoded = checksum.encode_to_vec();
+    assert_eq!(
+        encoded,
+        [
+            0x0D, 0x01, 0x00, 0x00, 0x00, 0x11, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+            0x1D, 0xFF, 0xFF, 0xFF, 0xFF, 0x21, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]
+    );
+    assert_eq!(checksum.encoded_len(), encoded.len());
+    assert_eq!(Checksum::decode(&encoded[..]).unwrap(), checksum);
+}
+
+#[test]
+fn test_float_double_fields() {
+    let point = Point { x: -0.0, y: 0.1 };
+    let encoded = point.encode_to_vec();
+    assert_eq!(
+        encoded,
+        [0x0D, 0x00, 0x00, 0x00, 0x80, 0x11, 0x9A, 0x99, 0x99, 0x99, 0x99, 0x99, 0xB9, 0x3F]
+    );
+    assert_eq!(point.encoded_len(), encoded.len());
+
+    let decoded = Point::decode(&encoded[..]).unwrap();
+    assert_eq!(decoded.x.to_bits(), (-0.0f32).to_bits());
+    assert_eq!(decoded.y, 0.1);
+}
+
+#[test]
+fn test_nan_payload_roundtrip() {
+    let point = Point {
+        x: f32::from_bits(0x7FC0_1234),
+        y: f64::from_bits(0xFFF8_0000_DEAD_BEEF),
+    };
+    let decoded = Point::decode(&point.encode_to_vec()[..]).unwrap();
+    assert_eq!(decoded.x.to_bits(), 0x7FC0_1234);
+    assert_eq!(decoded.y.to_bits(), 0xFFF8_0000_DEAD_BEEF);
+}
+
+#[test]
+fn test_string_bytes_fields() {
+    let profile = Profile {
+        name: "testing".to_string(),
+        avatar: vec![0xDE, 0xAD],
+    };
+    let encoded = profile.encode_to_vec();
+    assert_eq!(encoded, b"\x0A\x07testing\x12\x02\xDE\xAD");
+    assert_eq!(profile.encoded_len(), encoded.len());
+    assert_eq!(Profile::decode(&encoded[..]).unwrap(), profile);
+}
+
+#[test]
+fn test_invalid_utf8_names_field() {
+    let err = Profile::decode(&[0x0A, 0x02, 0xC3, 0x28][..]).unwrap_err();
+    assert_eq!(err.to_string(), "Profile.name: invalid utf-8 string");
+}
+
+#[test]
+fn test_bytes_fields_decode_into_bytes() {
+    let encoded = aproto::bytes::Bytes::from_static(b"\x0A\x02\x01\x02\x12\x01a");
+    let blob = Blob::decode(&mut encoded.clone()).unwrap();
+    let data: &aproto::bytes::Bytes = &blob.data;
+    assert_eq!(data.as_ref(), [0x01, 0x02]);
+    assert_eq!(blob.name, "a");
+    assert_eq!(blob.encode_to_vec(), encoded);
+
+    let blob = Blob {
+        parts: HashMap::from([(1, aproto::bytes::Bytes::from_static(b"b"))]),
+        ..Default::default()
+    };
+    assert_eq!(Blob::decode(&blob.encode_to_vec()[..]).unwrap(), blob);
+
+    let chunk = blob::Chunk {
+        data: aproto::bytes::Bytes::from_static(b"c"),
+    };
+    assert_eq!(chunk.data.as_ref(), b"c");
+}
+
+#[test]
+fn test_repeated_fields() {
+    let series = Series {
+        samples: vec![3, 270, 86942],
+        deltas: vec![-1, 1],
+        weights: vec![1.0],
+        ids: vec![1, 2],
+        labels: vec!["a".to_string(), "".to_string()],
+        blobs: vec![vec![0x01]],
+    };
+    #[rustfmt::skip]
+    let expected = [
+        // samples, packed
+        0x22, 0x06, 0x03, 0x8E, 0x02, 0x9E, 0xA7, 0x05,
+        // deltas, unpacked
+        0x28, 0x01, 0x28, 0x02,
+        // weights, packed
+        0x32, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF0, 0x3F,
+        // ids, unpacked
+        0x3D, 0x01, 0x00, 0x00, 0x00, 0x3D, 0x02, 0x00, 0x00, 0x00,
+        // labels
+        0x42, 0x01, b'a', 0x42, 0x00,
+        // blobs
+        0x4A, 0x01, 0x01,
+    ];
+    let encoded = series.encode_to_vec();
+    assert_eq!(encoded, expected);
+    assert_eq!(series.encoded_len(), encoded.len());
+    assert_eq!(Series::decode(&encoded[..]).unwrap(), series);
+}
+
+#[test]
+fn test_repeated_accepts_packed_and_unpacked() {
+    // `samples` unpacked and `deltas` packed, the opposite of their declarations,
+    // with a second `samples` run appended.
+    let bytes = [
+        0x20, 0x01, 0x20, 0x02, 0x2A, 0x02, 0x01, 0x02, 0x22, 0x01, 0x03,
+    ];
+    let series = Series::decode(&bytes[..]).unwrap();
+    assert_eq!(series.samples, [1, 2, 3]);
+    assert_eq!(series.deltas, [-1, 1]);
+}
+
+#[test]
+fn test_empty_repeated_fields_are_omitted() {
+    assert!(Series::default().encode_to_vec().is_empty());
+}
+
+#[test]
+fn test_map_fields() {
+    let directory = Directory {
+        ages: HashMap::from([("a".to_string(), 150)]),
+        profiles: HashMap::from([(
+            -1,
+            Profile {
+                name: "b".to_string(),
+                avatar: vec![],
+            },
+        )]),
+    };
+    #[rustfmt::skip]
+    let expected = [
+        // ages entry: key "a", value 150
+        0x0A, 0x06, 0x0A, 0x01, b'a', 0x10, 0x96, 0x01,
+        // profiles entry: key -1, value Profile { name: "b" }
+        0x12, 0x10,
+        0x08, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01,
+        0x12, 0x03, 0x0A, 0x01, b'b',
+    ];
+    let encoded = directory.encode_to_vec();
+    assert_eq!(encoded, expected);
+    assert_eq!(directory.encoded_len(), encoded.len());
+    assert_eq!(Directory::decode(&encoded[..]).unwrap(), directory);
+}
+
+#[test]
+fn test_map_entry_missing_key_or_value() {
+    // An `ages` entry with only a value and a `profiles` entry with only a key.
+    let bytes = [0x0A, 0x02, 0x10, 0x05, 0x12, 0x02, 0x08, 0x03];
+    let directory = Directory::decode(&bytes[..]).unwrap();
+    assert_eq!(directory.ages, HashMap::from([(String::new(), 5)]));
+    assert_eq!(directory.profiles, HashMap::from([(3, Profile::default())]));
+}
+
+#[test]
+fn test_nested_message_fields() {
+    let team = Team {
+        lead: Some(Profile {
+            name: "a".to_string(),
+            avatar: vec![],
+        }),
+        members: vec![Profile::default(), Profile::default()],
+        origin: None,
+    };
+    #[rustfmt::skip]
+    let expected = [
+        // lead, without the empty avatar
+        0x0A, 0x03, 0x0A, 0x01, b'a',
+        // members, both empty
+        0x12, 0x00,
+        0x12, 0x00,
+    ];
+    let encoded = team.encode_to_vec();
+    assert_eq!(encoded, expected);
+    assert_eq!(team.encoded_len(), encoded.len());
+    assert_eq!(Team::decode(&encoded[..]).unwrap(), team);
+}
+
+#[test]
+fn test_repeated_singular_message_is_merged() {
+    // `lead` appears twice, first with a name and then with an avatar.
+    let bytes = [0x0A, 0x03, 0x0A, 0x01, b'a', 0x0A, 0x03, 0x12, 0x01, 0xFF];
+    let team = Team::decode(&bytes[..]).unwrap();
+    assert_eq!(
+        team.lead,
+        Some(Profile {
+            name: "a".to_string(),
+            avatar: vec![0xFF],
+        })
+    );
+}
+
+#[test]
+fn test_empty_nested_message_is_present() {
+    let team = Team {
+        origin: Some(Point::default()),
+        ..Default::default()
+    };
+    let encoded = team.encode_to_vec();
+    assert_eq!(encoded[..2], [0x1A, encoded.len() as u8 - 2]);
+    assert_eq!(Team::decode(&encoded[..]).unwrap(), team);
+}
+
+#[test]
+fn test_recursive_message() {
+    let list = Node {
+        value: 1,
+        next: Some(Box::new(Node {
+            value: 2,
+            next: None,
+        })),
+    };
+    let encoded = list.encode_to_vec();
+    assert_eq!(list.encoded_len(), encoded.len());
+    assert_eq!(Node::decode(&encoded[..]).unwrap(), list);
+}
+
+#[test]
+fn test_recursion_limit() {
+    let list = |len: u32| {
+        (1..len).fold(Node::default(), |next, value| Node {
+            value,
+            next: Some(Box::new(next)),
+        })
+    };
+
+    // 100 messages may be nested in the outermost one.
+    let encoded = list(101).encode_to_vec();
+    assert_eq!(Node::decode(&encoded[..]).unwrap(), list(101));
+
+    let encoded = list(102).encode_to_vec();
+    let err = Node::decode(&encoded[..]).unwrap_err();
+    assert!(err
+        .to_string()
+        .ends_with("Node.next: recursion limit exceeded"));
+}
+
+#[test]
+fn test_nested_error_names_fields() {
+    let err = Team::decode(&[0x0A, 0x03, 0x0A, 0x01, 0xFF][..]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Team.lead: Profile.name: invalid utf-8 string"
+    );
+}
+
+#[test]
+fn test_default_values_are_omitted() {
+    let point = Point { x: 0.0, y: -0.0 };
+    // Negative zero is not the default, so it is encoded
+    assert_eq!(point.encode_to_vec()[0], 0x11);
+    assert_eq!(point.encoded_len(), 9);
+    assert!(Account::default().encode_to_vec().is_empty());
+    assert!(Profile::default().encode_to_vec().is_empty());
+}
+
+#[test]
+fn test_optional_fields_track_presence() {
+    let settings = Settings::default();
+    assert_eq!(settings.retries, None);
+    assert_eq!(settings.retries(), 0);
+    assert_eq!(settings.region(), "");
+    assert!(settings.encode_to_vec().is_empty());
+
+    // Set fields are encoded even when they hold the default
+    let settings = Settings {
+        retries: Some(0),
+        region: Some(String::new()),
+        ratio: Some(0.0),
+        verbose: Some(false),
+        timeout: 0,
+    };
+    #[rustfmt::skip]
+    let expected = [
+        0x08, 0x00,
+        0x12, 0x00,
+        0x19, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x20, 0x00,
+    ];
+    assert_eq!(settings.encode_to_vec(), expected);
+    assert_eq!(settings.encoded_len(), expected.len());
+    assert_eq!(Settings::decode(&expected[..]).unwrap(), settings);
+}
+
+#[test]
+fn test_keyword_field_names() {
+    let keywords = Keywords {
+        r#type: "a".to_string(),
+        r#match: Some(1),
+        self_: vec!["b".to_string()],
+        r#loop: HashMap::from([("c".to_string(), 2)]),
+        r#struct: Some(Sparse {
+            a: 3,
+            ..Default::default()
+        }),
+        r#async: Some(keywords::Async::Move(true)),
+    };
+    assert_eq!(keywords.r#match(), 1);
+
+    #[rustfmt::skip]
+    let expected = [
+        0x0a, 0x01, b'a',                         // type
+        0x10, 0x01,                               // match
+        0x1a, 0x01, b'b',                         // self
+        0x22, 0x05, 0x0a, 0x01, b'c', 0x10, 0x02, // loop
+        0x2a, 0x02, 0x08, 0x03,                   // struct
+        0x30, 0x01,                               // move
+    ];
+    assert_eq!(keywords.encode_to_vec(), expected);
+    assert_eq!(Keywords::decode(&expected[..]).unwrap(), keywords);
+}
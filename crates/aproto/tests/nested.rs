@@ -0,0 +1,158 @@
+use aproto::Message;
+
+aproto::message! {
+    message Tree {
+        string name = 1;
+        Node root = 2;
+        Order order = 3;
+
+        message Node {
+            uint32 value = 1;
+            Kind kind = 2;
+            repeated Node children = 3;
+            Leaf leaf = 4;
+            Order order = 5;
+            map<string, Leaf> leaves = 6;
+
+            message Leaf {
+                bool last = 1;
+                repeated Node owners = 2;
+            }
+
+            enum Kind {
+                BRANCH = 0;
+                LEAF = 1;
+            }
+        }
+
+        enum Order {
+            DEPTH_FIRST = 0;
+            BREADTH_FIRST = 1;
+        }
+    }
+}
+
+aproto::message! {
+    message Scope {
+        message Binding {
+            Scope body = 1;
+        }
+        Binding binding = 1;
+        repeated Scope children = 2;
+    }
+}
+
+aproto::message! {
+    message Type {
+        Match match_ = 1;
+        oneof kind {
+            string name = 2;
+            Match pattern = 3;
+        }
+
+        message Match {
+            string value = 1;
+        }
+    }
+}
+
+#[test]
+fn test_nested_types_are_generated_in_modules() {
+    let leaf = tree::node::Leaf {
+        last: true,
+        owners: vec![],
+    };
+    let node = tree::Node {
+        value: 1,
+        kind: tree::node::Kind::Leaf.into(),
+        children: vec![],
+        leaf: Some(leaf),
+        order: tree::Order::BreadthFirst.into(),
+        leaves: Default::default(),
+    };
+    let tree = Tree {
+        name: "t".to_string(),
+        root: Some(node),
+        order: tree::Order::DepthFirst.into(),
+    };
+
+    #[rustfmt::skip]
+    let expected = [
+        0x0a, 0x01, b't',   // name = "t"
+        0x12, 0x0a,         // root
+        0x08, 0x01,         //   value = 1
+        0x10, 0x01,         //   kind = LEAF
+        0x22, 0x02,         //   leaf
+        0x08, 0x01,         //     last = true
+        0x28, 0x01,         //   order = BREADTH_FIRST
+                            // order = DEPTH_FIRST, the default
+    ];
+    assert_eq!(tree.encode_to_vec(), expected);
+}
+
+#[test]
+fn test_nested_roundtrip() {
+    let mut root = tree::Node {
+        value: 1,
+        ..Default::default()
+    };
+    root.children.push(tree::Node {
+        value: 2,
+        kind: tree::node::Kind::Leaf.into(),
+        ..Default::default()
+    });
+    root.leaves.insert(
+        "x".to_string(),
+        tree::node::Leaf {
+            last: true,
+            owners: vec![tree::Node {
+                value: 3,
+                ..Default::default()
+            }],
+        },
+    );
+    let tree = Tree {
+        name: "tree".to_string(),
+        root: Some(root),
+        order: tree::Order::BreadthFirst.into(),
+    };
+
+    let bytes = tree.encode_to_vec();
+    assert_eq!(bytes.len(), tree.encoded_len());
+    assert_eq!(Tree::decode(&mut bytes.as_slice()).unwrap(), tree);
+}
+
+#[test]
+fn test_nested_decode_error_names_full_message() {
+    // root { leaf { last = <truncated varint> } }
+    let bytes = [0x12, 0x04, 0x22, 0x02, 0x08, 0x80];
+    let err = Tree::decode(&mut bytes.as_slice()).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("Tree.root: Tree.Node.leaf: Tree.Node.Leaf.last: "));
+}
+
+#[test]
+fn test_keyword_module_names() {
+    let ty = Type {
+        match_: Some(r#type::Match {
+            value: "a".to_string(),
+        }),
+        kind: Some(r#type::Kind::Pattern(r#type::Match::default())),
+    };
+    let bytes = ty.encode_to_vec();
+    assert_eq!(Type::decode(&mut bytes.as_slice()).unwrap(), ty);
+}
+
+#[test]
+fn test_nested_message_referencing_parent() {
+    let scope = Scope {
+        binding: Some(Box::new(scope::Binding {
+            body: Some(Box::new(Scope::default())),
+        })),
+        children: vec![Scope::default()],
+    };
+    let encoded = scope.encode_to_vec();
+    assert_eq!(scope.encoded_len(), encoded.len());
+    assert_eq!(Scope::decode(&encoded[..]).unwrap(), scope);
+}
@@ -0,0 +1,115 @@
+use aproto::{AprotoError, Message};
+
+aproto::message! {
+    message Attachment {
+        string name = 1;
+        uint32 size = 2;
+    }
+}
+
+aproto::message! {
+    message Event {
+        uint64 id = 1;
+        oneof payload {
+            string text = 2;
+            Attachment attachment = 3;
+            Level level = 4;
+            Event parent = 5;
+        }
+
+        enum Level {
+            INFO = 0;
+            WARN = 1;
+        }
+    }
+}
+
+#[test]
+fn test_oneof_encode() {
+    let event = Event {
+        id: 1,
+        payload: Some(event::Payload::Text("hi".to_string())),
+    };
+    assert_eq!(event.encode_to_vec(), [0x08, 0x01, 0x12, 0x02, b'h', b'i']);
+    assert_eq!(event.encoded_len(), 6);
+}
+
+#[test]
+fn test_oneof_unset_is_omitted() {
+    let event = Event {
+        id: 1,
+        payload: None,
+    };
+    assert_eq!(event.encode_to_vec(), [0x08, 0x01]);
+}
+
+#[test]
+fn test_oneof_set_to_default_is_encoded() {
+    let event = Event {
+        id: 0,
+        payload: Some(event::Payload::Level(event::Level::Info.into())),
+    };
+    // `id` holds its default and is skipped, while the oneof member is not
+    assert_eq!(event.encode_to_vec(), [0x20, 0x00]);
+}
+
+#[test]
+fn test_oneof_roundtrip() {
+    let payloads = [
+        event::Payload::Text("hello".to_string()),
+        event::Payload::Attachment(Attachment {
+            name: "a.txt".to_string(),
+            size: 42,
+        }),
+        event::Payload::Level(event::Level::Warn.into()),
+        event::Payload::Parent(Box::new(Event {
+            id: 7,
+            payload: Some(event::Payload::Text("root".to_string())),
+        })),
+    ];
+    for payload in payloads {
+        let event = Event {
+            id: 3,
+            payload: Some(payload),
+        };
+        let bytes = event.encode_to_vec();
+        assert_eq!(bytes.len(), event.encoded_len());
+        assert_eq!(Event::decode(&mut bytes.as_slice()).unwrap(), event);
+    }
+}
+
+#[test]
+fn test_oneof_last_member_wins() {
+    #[rustfmt::skip]
+    let bytes = [
+        0x12, 0x02, b'h', b'i', // text = "hi"
+        0x20, 0x01,             // level = WARN
+    ];
+    let event = Event::decode(&mut bytes.as_slice()).unwrap();
+    assert_eq!(event.payload, Some(event::Payload::Level(1)));
+}
+
+#[test]
+fn test_oneof_message_member_merges() {
+    #[rustfmt::skip]
+    let bytes = [
+        0x1a, 0x03, 0x0a, 0x01, b'a', // attachment { name = "a" }
+        0x1a, 0x02, 0x10, 0x05,       // attachment { size = 5 }
+    ];
+    let event = Event::decode(&mut bytes.as_slice()).unwrap();
+    assert_eq!(
+        event.payload,
+        Some(event::Payload::Attachment(Attachment {
+            name: "a".to_string(),
+            size: 5,
+        }))
+    );
+}
+
+#[test]
+fn test_oneof_decode_error_names_member() {
+    let bytes = [0x12, 0x01, 0xff];
+    let err = Event::decode(&mut bytes.as_slice()).unwrap_err();
+    assert!(matches!(err, AprotoError::Field { field: "text", .. }));
+    assert_eq!(err.to_string(), "Event.text: invalid utf-8 string");
+}
@@ -0,0 +1,98 @@
+use aproto::Message;
+
+aproto::message! {
+    message Legacy {
+        uint32 id = 1 [json_name = "identifier"];
+        string old_name = 2 [deprecated = true];
+        repeated int32 codes = 3 [packed = false, deprecated = false];
+        map<string, int32> counts = 4 [deprecated = true];
+        oneof choice {
+            int32 old_choice = 5 [deprecated = true];
+            int32 new_choice = 6;
+        }
+    }
+}
+
+aproto::proto! {
+    message Flags {
+        repeated Flag flags = 1 [packed = false];
+
+        enum Flag {
+            NONE = 0;
+            ON = 1;
+        }
+    }
+}
+
+mod proto2 {
+    aproto::proto! {
+        syntax = "proto2";
+
+        message Levels {
+            repeated Level levels = 1 [packed = true];
+        }
+
+        enum Level {
+            LOW = 1;
+            HIGH = 2;
+        }
+    }
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_options_do_not_change_encoding() {
+    let legacy = Legacy {
+        id: 1,
+        old_name: "a".to_string(),
+        codes: vec![1, 2],
+        counts: Default::default(),
+        choice: Some(legacy::Choice::OldChoice(3)),
+    };
+
+    #[rustfmt::skip]
+    let expected = [
+        0x08, 0x01,       // id = 1
+        0x12, 0x01, b'a', // old_name = "a"
+        0x18, 0x01,       // codes, unpacked
+        0x18, 0x02,
+        0x28, 0x03,       // old_choice = 3
+    ];
+    assert_eq!(legacy.encode_to_vec(), expected);
+    assert_eq!(Legacy::decode(&mut expected.as_slice()).unwrap(), legacy);
+}
+
+#[test]
+fn test_packed_enum_fields() {
+    let flags = Flags {
+        flags: vec![flags::Flag::On.into(), flags::Flag::None.into()],
+    };
+    let expected = [0x08, 0x01, 0x08, 0x00];
+    assert_eq!(flags.encode_to_vec(), expected);
+    assert_eq!(Flags::decode(&mut expected.as_slice()).unwrap(), flags);
+
+    let levels = proto2::Levels {
+        levels: vec![proto2::Level::Low.into(), proto2::Level::High.into()],
+    };
+    let expected = [0x0a, 0x02, 0x01, 0x02];
+    assert_eq!(levels.encode_to_vec(), expected);
+    assert_eq!(
+        proto2::Levels::decode(&mut expected.as_slice()).unwrap(),
+        levels
+    );
+}
+
+#[test]
+fn test_json_names() {
+    assert_eq!(
+        Legacy::JSON_NAMES,
+        [
+            ("id", "identifier"),
+            ("old_name", "oldName"),
+            ("codes", "codes"),
+            ("counts", "counts"),
+            ("old_choice", "oldChoice"),
+            ("new_choice", "newChoice"),
+        ]
+    );
+}
@@ -0,0 +1,167 @@
+use aproto::Message;
+
+aproto::proto! {
+    syntax = "proto3";
+    package people.v1;
+
+    message Person {
+        string name = 1;
+        Status status = 2;
+        Address address = 3;
+        map<string, Status> roles = 4;
+        repeated Status history = 5;
+        reserved 6, 8 to 10;
+        reserved "age";
+    }
+
+    enum Status {
+        UNKNOWN = 0;
+        ACTIVE = 1;
+    }
+
+    message Address {
+        string city = 1;
+        Country country = 2;
+
+        enum Country {
+            UNSPECIFIED = 0;
+            NL = 1;
+        }
+    }
+}
+
+aproto::proto! {
+    message Empty {}
+}
+
+mod recursive {
+    aproto::proto! {
+        #![aproto(bytes = "bytes")]
+
+        message Expr {
+            int64 value = 1;
+            Call call = 2;
+        }
+
+        message Call {
+            string name = 1;
+            oneof arg {
+                Expr expr = 2;
+                bytes raw = 3;
+            }
+            Expr fallback = 4;
+        }
+    }
+}
+
+mod dotted {
+    aproto::proto! {
+        package shop.v1;
+
+        message Order {
+            Item.Kind kind = 1;
+            .shop.v1.Item item = 2;
+            map<string, v1.Item> items = 3;
+            repeated shop.v1.Item.Kind kinds = 4;
+        }
+
+        message Item {
+            string name = 1;
+            Kind kind = 2;
+
+            enum Kind {
+                UNSPECIFIED = 0;
+                BOOK = 1;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_proto_cross_references() {
+    let person = Person {
+        name: "ann".to_string(),
+        status: Status::Active.into(),
+        address: Some(Address {
+            city: "x".to_string(),
+            country: address::Country::Nl.into(),
+        }),
+        roles: [("admin".to_string(), Status::Active.into())].into(),
+        history: vec![Status::Unknown.into(), Status::Active.into()],
+    };
+
+    #[rustfmt::skip]
+    let expected = [
+        0x0a, 0x03, b'a', b'n', b'n',             // name = "ann"
+        0x10, 0x01,                               // status = ACTIVE
+        0x1a, 0x05,                               // address
+        0x0a, 0x01, b'x',                         //   city = "x"
+        0x10, 0x01,                               //   country = NL
+        0x22, 0x09,                               // roles entry
+        0x0a, 0x05, b'a', b'd', b'm', b'i', b'n', //   key = "admin"
+        0x10, 0x01,                               //   value = ACTIVE
+        0x2a, 0x02, 0x00, 0x01,                   // history, packed
+    ];
+    assert_eq!(person.encode_to_vec(), expected);
+    assert_eq!(Person::decode(&mut expected.as_slice()).unwrap(), person);
+}
+
+#[test]
+fn test_proto_single_message() {
+    assert!(Empty::default().encode_to_vec().is_empty());
+}
+
+#[test]
+fn test_mutually_recursive_messages() {
+    use recursive::{call, Call, Expr};
+
+    let expr = Expr {
+        value: 1,
+        call: Some(Box::new(Call {
+            name: "f".to_string(),
+            arg: Some(call::Arg::Expr(Box::new(Expr {
+                value: 2,
+                call: None,
+            }))),
+            fallback: Some(Box::new(Expr::default())),
+        })),
+    };
+    let encoded = expr.encode_to_vec();
+    assert_eq!(expr.encoded_len(), encoded.len());
+    assert_eq!(Expr::decode(&encoded[..]).unwrap(), expr);
+
+    let raw = call::Arg::Raw(aproto::bytes::Bytes::from_static(b"x"));
+    assert_ne!(Some(raw), expr.call.unwrap().arg);
+}
+
+#[test]
+fn test_proto_dotted_references() {
+    use dotted::{item::Kind, Item, Order};
+
+    let item = Item {
+        name: "a".to_string(),
+        kind: Kind::Book.into(),
+    };
+    let order = Order {
+        kind: Kind::Book.into(),
+        item: Some(item.clone()),
+        items: [("b".to_string(), item)].into(),
+        kinds: vec![Kind::Book.into()],
+    };
+
+    #[rustfmt::skip]
+    let expected = [
+        0x08, 0x01,                   // kind = BOOK
+        0x12, 0x05,                   // item
+        0x0a, 0x01, b'a',             //   name = "a"
+        0x10, 0x01,                   //   kind = BOOK
+        0x1a, 0x0a,                   // items entry
+        0x0a, 0x01, b'b',             //   key = "b"
+        0x12, 0x05,                   //   value
+        0x0a, 0x01, b'a',             //     name = "a"
+        0x10, 0x01,                   //     kind = BOOK
+        0x22, 0x01, 0x01,             // kinds, packed
+    ];
+    assert_eq!(order.encode_to_vec(), expected);
+    assert_eq!(Order::decode(&mut expected.as_slice()).unwrap(), order);
+}
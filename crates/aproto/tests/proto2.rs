@@ -0,0 +1,192 @@
+use aproto::{AprotoError, Message};
+
+aproto::proto! {
+    syntax = "proto2";
+
+    message SearchRequest {
+        required string query = 1;
+        optional int32 page = 2 [default = 1];
+        optional int32 offset = 3 [default = -10];
+        optional double ratio = 4 [default = 0.5];
+        optional float limit = 5 [default = inf];
+        optional bool exact = 6 [default = true];
+        optional string locale = 7 [default = "en"];
+        optional bytes token = 8 [default = "abc"];
+        optional Corpus corpus = 9 [default = WEB];
+        optional Corpus fallback = 10;
+        optional uint32 count = 11;
+        repeated int32 ids = 12;
+        repeated int32 packed_ids = 13 [packed = true];
+        optional int32 mode = 14 [default = 017];
+        optional bytes raw = 15 [default = b"\xff\x00"];
+    }
+
+    enum Corpus {
+        UNIVERSAL = 1;
+        WEB = 2;
+    }
+
+    message SearchResponse {
+        repeated group Result = 1 {
+            required string url = 2;
+            optional string title = 3;
+        }
+        optional SearchRequest request = 4;
+        map<string, SearchRequest> history = 5;
+        oneof source {
+            SearchRequest origin = 6;
+            string cache = 7;
+        }
+    }
+}
+
+mod header {
+    aproto::proto! {
+        #![aproto(bytes = "bytes")]
+        syntax = "proto2";
+
+        message Header {
+            optional bytes magic = 1 [default = "\x7fELF"];
+        }
+    }
+}
+
+#[test]
+fn test_proto2_defaults() {
+    let request = SearchRequest::default();
+    assert_eq!(request.query(), "");
+    assert_eq!(request.page(), 1);
+    assert_eq!(request.offset(), -10);
+    assert_eq!(request.ratio(), 0.5);
+    assert_eq!(request.limit(), f32::INFINITY);
+    assert!(request.exact());
+    assert_eq!(request.locale(), "en");
+    assert_eq!(request.token(), b"abc");
+    assert_eq!(request.corpus(), i32::from(Corpus::Web));
+    assert_eq!(request.fallback(), i32::from(Corpus::Universal));
+    assert_eq!(request.count(), 0);
+    // A leading zero makes the literal octal
+    assert_eq!(request.mode(), 15);
+    assert_eq!(request.raw(), [0xff, 0x00]);
+
+    let request = SearchRequest {
+        page: Some(3),
+        locale: Some("nl".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(request.page(), 3);
+    assert_eq!(request.locale(), "nl");
+}
+
+#[test]
+fn test_proto2_bytes_default() {
+    use header::Header;
+
+    let header = Header::default();
+    assert_eq!(header.magic(), b"\x7fELF");
+    let header = Header {
+        magic: Some(aproto::bytes::Bytes::from_static(b"MZ")),
+    };
+    assert_eq!(header.magic(), b"MZ");
+    assert_eq!(header.encode_to_vec(), [0x0a, 0x02, b'M', b'Z']);
+}
+
+#[test]
+fn test_proto2_encoding() {
+    let request = SearchRequest {
+        query: Some("a".to_string()),
+        page: Some(0),
+        ids: vec![1, 2],
+        packed_ids: vec![1, 2],
+        ..Default::default()
+    };
+
+    #[rustfmt::skip]
+    let expected = [
+        0x0a, 0x01, b'a',       // query = "a"
+        0x10, 0x00,             // page = 0, set so encoded
+        0x60, 0x01, 0x60, 0x02, // ids, unpacked by default
+        0x6a, 0x02, 0x01, 0x02, // packed_ids
+    ];
+    assert_eq!(request.encode_to_vec(), expected);
+    assert_eq!(
+        SearchRequest::decode(&mut expected.as_slice()).unwrap(),
+        request
+    );
+}
+
+#[test]
+fn test_proto2_groups() {
+    let response = SearchResponse {
+        result: vec![
+            search_response::Result {
+                url: Some("x".to_string()),
+                title: None,
+            },
+            search_response::Result {
+                url: Some("y".to_string()),
+                title: Some("z".to_string()),
+            },
+        ],
+        ..Default::default()
+    };
+
+    #[rustfmt::skip]
+    let expected = [
+        0x0b,                   // result, start group
+        0x12, 0x01, b'x',       //   url = "x"
+        0x0c,                   // end group
+        0x0b,                   // result, start group
+        0x12, 0x01, b'y',       //   url = "y"
+        0x1a, 0x01, b'z',       //   title = "z"
+        0x0c,                   // end group
+    ];
+    assert_eq!(response.encode_to_vec(), expected);
+    assert_eq!(response.encoded_len(), expected.len());
+    assert_eq!(
+        SearchResponse::decode(&mut expected.as_slice()).unwrap(),
+        response
+    );
+}
+
+#[test]
+fn test_proto2_missing_required_fields() {
+    let err = SearchRequest::decode(&mut [0x10, 0x01].as_slice()).unwrap_err();
+    assert!(matches!(
+        &err,
+        AprotoError::Field { message: "SearchRequest", field: "query", source }
+            if matches!(**source, AprotoError::MissingRequiredField)
+    ));
+    assert_eq!(
+        err.to_string(),
+        "SearchRequest.query: missing required field"
+    );
+
+    // A required field may be split across a message that appears twice.
+    let mut buf = [0x22, 0x02, 0x10, 0x01, 0x22, 0x03, 0x0a, 0x01, b'a'].as_slice();
+    let response = SearchResponse::decode(&mut buf).unwrap();
+    assert_eq!(response.request.unwrap().query(), "a");
+
+    let cases: [(&[u8], &str); 4] = [
+        (
+            &[0x22, 0x00],
+            "SearchResponse.request: SearchRequest.query: missing required field",
+        ),
+        (
+            &[0x0b, 0x1a, 0x01, b'z', 0x0c],
+            "SearchResponse.result: SearchResponse.Result.url: missing required field",
+        ),
+        (
+            &[0x2a, 0x05, 0x0a, 0x01, b'k', 0x12, 0x00],
+            "SearchResponse.history: SearchRequest.query: missing required field",
+        ),
+        (
+            &[0x32, 0x00],
+            "SearchResponse.origin: SearchRequest.query: missing required field",
+        ),
+    ];
+    for (mut buf, expected) in cases {
+        let err = SearchResponse::decode(&mut buf).unwrap_err();
+        assert_eq!(err.to_string(), expected);
+    }
+}